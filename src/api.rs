@@ -0,0 +1,108 @@
+//! JSON counterparts of the HTML pages, mounted at `/api/v1`.
+
+use std::cmp::max;
+
+use rocket::request::Form;
+use rocket::Route;
+use rocket_contrib::json::Json;
+
+use events_frontend::{GraphQLClient, PageMetadata, PerPage, VisitorTimezone};
+use events_frontend::error;
+
+use crate::{DayEventContext, Event, EventFilter, ListPage, Location, Organizer, Tag, TagSummary};
+
+pub const PREFIX: &str = "/api/v1";
+
+#[derive(Serialize)]
+struct Page<T> {
+    data: Vec<T>,
+    pagination: PageMetadata,
+}
+
+impl<T> From<ListPage<T>> for Page<T> {
+    fn from(page: ListPage<T>) -> Page<T> {
+        Page {
+            data: page.entries,
+            pagination: page.paginator.into(),
+        }
+    }
+}
+
+/// A location or organizer with its events, like on its HTML page.
+#[derive(Serialize)]
+struct WithEvents<T> {
+    #[serde(flatten)]
+    entity: T,
+    events: Vec<Event>,
+}
+
+/// Events grouped by the day (in the visitor's timezone) they start on, with the same filters as `/events/<page>`.
+#[get("/events/<page>?<filter..>")]
+fn events(client: GraphQLClient, timezone: VisitorTimezone, page: Option<u32>, filter: Form<EventFilter>, per_page: PerPage) -> error::Result<Json<Page<DayEventContext>>> {
+    let events = filter.events_in_range(&client, timezone.0)?;
+
+    Ok(Json(crate::event_page(events, timezone.0, max(1, page.unwrap_or(1)), &filter, per_page, PREFIX).into()))
+}
+
+#[get("/event/<id>")]
+fn event(client: GraphQLClient, id: i64) -> error::Result<Json<Event>> {
+    crate::get_event(&client, id).map(Json)
+}
+
+#[get("/locations/<page>")]
+fn locations(client: GraphQLClient, page: Option<u32>, per_page: PerPage) -> error::Result<Json<Page<Location>>> {
+    crate::location_page(&client, max(1, page.unwrap_or(1)), per_page, PREFIX)
+        .map(|page| Json(page.into()))
+}
+
+#[get("/location/<id>")]
+fn location(client: GraphQLClient, id: i64) -> error::Result<Json<WithEvents<Location>>> {
+    let location = crate::get_location(&client, id)?;
+    let events = crate::get_events_for_location(&client, location.id)?;
+
+    Ok(Json(WithEvents {
+        entity: location,
+        events,
+    }))
+}
+
+#[get("/organizers/<page>")]
+fn organizers(client: GraphQLClient, page: Option<u32>, per_page: PerPage) -> error::Result<Json<Page<Organizer>>> {
+    crate::organizer_page(&client, max(1, page.unwrap_or(1)), per_page, PREFIX)
+        .map(|page| Json(page.into()))
+}
+
+#[get("/organizer/<id>")]
+fn organizer(client: GraphQLClient, id: i64) -> error::Result<Json<WithEvents<Organizer>>> {
+    let organizer = crate::get_organizer(&client, id)?;
+    let events = crate::get_events_for_organizer(&client, organizer.id)?;
+
+    Ok(Json(WithEvents {
+        entity: organizer,
+        events,
+    }))
+}
+
+#[get("/tags?<sort>&<page>")]
+fn tags(client: GraphQLClient, sort: Option<String>, page: Option<u32>, per_page: PerPage) -> error::Result<Json<Page<TagSummary>>> {
+    crate::tag_page(&client, sort.as_deref() == Some("name"), max(1, page.unwrap_or(1)), per_page, PREFIX)
+        .map(|page| Json(page.into()))
+}
+
+#[get("/tag/<id>")]
+fn tag(client: GraphQLClient, id: i64) -> error::Result<Json<Tag>> {
+    crate::get_tag(&client, id).map(Json)
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        events,
+        event,
+        locations,
+        location,
+        organizers,
+        organizer,
+        tags,
+        tag,
+    ]
+}
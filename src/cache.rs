@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use rocket::Config;
+
+/// Seconds a response stays fresh per operation name.
+///
+/// Responses of operations without a ttl (or a ttl of 0) are only stored as fallback for when the backend is unavailable.
+const DEFAULT_TTLS: [(&str, u64); 5] = [
+    ("Event", 30),
+    ("Location", 60),
+    ("Organizer", 60),
+    ("Tag", 300),
+    ("Pagination", 60),
+];
+
+#[derive(Debug)]
+struct Entry {
+    data: serde_json::Value,
+    fetched_at: Instant,
+    fetched: DateTime<Utc>,
+    used_at: Instant,
+    invalidated: bool,
+}
+
+/// In-process cache for the `data` of backend responses, keyed by operation name and serialized variables.
+///
+/// The keys contain the variables of the requests, so the number of entries is limited to `max_entries`.
+#[derive(Debug)]
+pub struct ResponseCache {
+    ttls: HashMap<String, Duration>,
+    /// entries older than this aren't used as fallback anymore and are removed first
+    max_stale: Duration,
+    max_entries: usize,
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl ResponseCache {
+    pub fn new(ttls: HashMap<String, Duration>, max_stale: Duration, max_entries: usize) -> ResponseCache {
+        ResponseCache {
+            ttls,
+            max_stale,
+            max_entries,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Starts with the default ttls, entries of the `cache_ttl` table of the rocket config (in seconds) override them.
+    ///
+    /// The size is limited by the `cache_max_stale` (in seconds, default one day) and `cache_max_entries` (default 1000) settings.
+    pub fn from_config(config: &Config) -> ResponseCache {
+        let mut ttls = DEFAULT_TTLS
+            .iter()
+            .map(|(name, seconds)| (name.to_string(), Duration::from_secs(*seconds)))
+            .collect::<HashMap<String, Duration>>();
+
+        if let Ok(table) = config.get_table("cache_ttl") {
+            for (name, value) in table {
+                if let Some(seconds) = value.as_integer() {
+                    ttls.insert(name.clone(), Duration::from_secs(seconds.max(0) as u64));
+                }
+            }
+        }
+
+        let max_stale = Duration::from_secs(config.get_int("cache_max_stale").unwrap_or(24 * 60 * 60).max(0) as u64);
+        let max_entries = config.get_int("cache_max_entries").unwrap_or(1000).max(1) as usize;
+
+        ResponseCache::new(ttls, max_stale, max_entries)
+    }
+
+    pub fn key(operation_name: &str, variables: &str) -> String {
+        format!("{}:{}", operation_name, variables)
+    }
+
+    /// Returns the stored data as long as it's younger than the ttl of its operation and wasn't invalidated.
+    pub fn get(&self, operation_name: &str, key: &str) -> Option<serde_json::Value> {
+        let ttl = *self.ttls.get(operation_name)?;
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+
+        entries
+            .get_mut(key)
+            .filter(|entry| !entry.invalidated && entry.fetched_at.elapsed() < ttl)
+            .map(|entry| {
+                entry.used_at = Instant::now();
+                entry.data.clone()
+            })
+    }
+
+    /// Returns the last stored data regardless of its age together with the time it was fetched, used while the backend is down.
+    pub fn get_stale(&self, key: &str) -> Option<(serde_json::Value, DateTime<Utc>)> {
+        let entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+
+        entries
+            .get(key)
+            .filter(|entry| entry.fetched_at.elapsed() < self.max_stale)
+            .map(|entry| (entry.data.clone(), entry.fetched))
+    }
+
+    /// Stores a response, if the cache is full the entries too old for the fallback or else the least recently used one are removed.
+    pub fn insert(&self, key: String, data: serde_json::Value) {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if !entries.contains_key(&key) && entries.len() >= self.max_entries {
+            let max_stale = self.max_stale;
+            entries.retain(|_, entry| entry.fetched_at.elapsed() < max_stale);
+
+            if entries.len() >= self.max_entries {
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.used_at)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+
+        let now = Instant::now();
+        entries.insert(key, Entry {
+            data,
+            fetched_at: now,
+            fetched: Utc::now(),
+            used_at: now,
+            invalidated: false,
+        });
+    }
+
+    /// Marks every entry as outdated while keeping it as fallback, a mutation can change the result of any query (counts, tags, locations of events, ...).
+    pub fn invalidate(&self) {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values_mut()
+            .for_each(|entry| entry.invalidated = true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::ResponseCache;
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    fn cache(ttl: Duration, max_stale: Duration, max_entries: usize) -> ResponseCache {
+        let ttls = vec![("Event".to_string(), ttl), ("Tag".to_string(), Duration::from_secs(0))]
+            .into_iter()
+            .collect::<HashMap<String, Duration>>();
+
+        ResponseCache::new(ttls, max_stale, max_entries)
+    }
+
+    #[test]
+    fn expires_after_the_ttl() {
+        let cache = cache(Duration::from_millis(50), HOUR, 10);
+        cache.insert("Event:1".to_string(), json!(1));
+
+        assert_eq!(cache.get("Event", "Event:1"), Some(json!(1)));
+        sleep(Duration::from_millis(60));
+        assert_eq!(cache.get("Event", "Event:1"), None);
+        assert_eq!(cache.get_stale("Event:1").map(|(data, _)| data), Some(json!(1)));
+    }
+
+    #[test]
+    fn ttl_of_zero_only_stores_a_fallback() {
+        let cache = cache(HOUR, HOUR, 10);
+        cache.insert("Tag:1".to_string(), json!(1));
+        cache.insert("Location:1".to_string(), json!(2));
+
+        assert_eq!(cache.get("Tag", "Tag:1"), None);
+        assert_eq!(cache.get("Location", "Location:1"), None);
+        assert_eq!(cache.get_stale("Tag:1").map(|(data, _)| data), Some(json!(1)));
+        assert_eq!(cache.get_stale("Location:1").map(|(data, _)| data), Some(json!(2)));
+    }
+
+    #[test]
+    fn invalidated_entries_stay_as_fallback() {
+        let cache = cache(HOUR, HOUR, 10);
+        cache.insert("Event:1".to_string(), json!(1));
+        cache.invalidate();
+
+        assert_eq!(cache.get("Event", "Event:1"), None);
+        assert_eq!(cache.get_stale("Event:1").map(|(data, _)| data), Some(json!(1)));
+
+        cache.insert("Event:1".to_string(), json!(2));
+        assert_eq!(cache.get("Event", "Event:1"), Some(json!(2)));
+    }
+
+    #[test]
+    fn no_fallback_older_than_max_stale() {
+        let cache = cache(HOUR, Duration::from_millis(50), 10);
+        cache.insert("Event:1".to_string(), json!(1));
+
+        assert!(cache.get_stale("Event:1").is_some());
+        sleep(Duration::from_millis(60));
+        assert!(cache.get_stale("Event:1").is_none());
+    }
+
+    #[test]
+    fn removes_the_least_recently_used_entry() {
+        let cache = cache(HOUR, HOUR, 2);
+        cache.insert("Event:1".to_string(), json!(1));
+        sleep(Duration::from_millis(2));
+        cache.insert("Event:2".to_string(), json!(2));
+        sleep(Duration::from_millis(2));
+        cache.get("Event", "Event:1");
+        sleep(Duration::from_millis(2));
+        cache.insert("Event:3".to_string(), json!(3));
+
+        assert!(cache.get_stale("Event:1").is_some());
+        assert!(cache.get_stale("Event:2").is_none());
+        assert!(cache.get_stale("Event:3").is_some());
+    }
+
+    #[test]
+    fn removes_entries_older_than_max_stale_first() {
+        let cache = cache(HOUR, Duration::from_millis(50), 2);
+        cache.insert("Event:1".to_string(), json!(1));
+        sleep(Duration::from_millis(60));
+        cache.insert("Event:2".to_string(), json!(2));
+        cache.insert("Event:3".to_string(), json!(3));
+
+        assert_eq!(cache.entries.lock().unwrap().len(), 2);
+        assert!(cache.get_stale("Event:2").is_some());
+        assert!(cache.get_stale("Event:3").is_some());
+    }
+}
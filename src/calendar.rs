@@ -0,0 +1,500 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime, Offset, TimeZone};
+use chrono_tz::{OffsetComponents, Tz};
+use ics::properties::TzName;
+use ics::ZoneTime;
+
+/// Converts a local time of `timezone` to UTC, times skipped by a DST change are moved forward by an hour.
+///
+/// `None` for times too close to the limits of `NaiveDateTime`, where the offset can't be applied.
+pub fn local_to_utc(timezone: Tz, local: NaiveDateTime) -> Option<NaiveDateTime> {
+    // chrono-tz applies the offsets with unchecked arithmetic, offsets are always less than a day
+    local.checked_sub_signed(Duration::days(1))?;
+    local.checked_add_signed(Duration::days(1))?;
+
+    timezone
+        .from_local_datetime(&local)
+        .earliest()
+        .or_else(|| local
+            .checked_add_signed(Duration::hours(1))
+            .and_then(|local| timezone.from_local_datetime(&local).earliest()))
+        .map(|time| time.naive_utc())
+}
+
+/// Escapes a value of the iCalendar type TEXT, including line breaks.
+pub fn text(value: &str) -> String {
+    ics::escape_text(value).replace('\n', "\\n")
+}
+
+/// Formats a UTC offset as `+HHMM` (or `+HHMMSS` for the historic ones with seconds).
+fn format_offset(seconds: i32) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.abs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+    if seconds != 0 {
+        format!("{}{:02}{:02}{:02}", sign, hours, minutes, seconds)
+    } else {
+        format!("{}{:02}{:02}", sign, hours, minutes)
+    }
+}
+
+#[derive(PartialEq)]
+struct Observance {
+    offset: i32,
+    daylight: bool,
+    name: String,
+}
+
+impl Observance {
+    fn at(timezone: Tz, time: &NaiveDateTime) -> Observance {
+        let offset = timezone.offset_from_utc_datetime(time);
+
+        Observance {
+            offset: offset.fix().local_minus_utc(),
+            daylight: !offset.dst_offset().is_zero(),
+            name: offset.to_string(),
+        }
+    }
+
+    /// `start` is the UTC instant this observance begins, DTSTART is written in the local time before it.
+    fn to_zone_time(&self, previous_offset: i32, start: NaiveDateTime) -> ZoneTime<'static> {
+        let dtstart = (start + Duration::seconds(i64::from(previous_offset))).format("%Y%m%dT%H%M%S").to_string();
+        let from = format_offset(previous_offset);
+        let to = format_offset(self.offset);
+        let mut zone_time = if self.daylight {
+            ZoneTime::daylight(dtstart, from, to)
+        } else {
+            ZoneTime::standard(dtstart, from, to)
+        };
+        zone_time.push(TzName::new(self.name.clone()));
+
+        zone_time
+    }
+}
+
+/// How many years before and after the current one a VTIMEZONE covers at most.
+///
+/// Building it walks the range day by day, so it must not follow events with absurd dates.
+pub const TIMEZONE_YEARS: i32 = 10;
+
+/// Builds a VTIMEZONE with one observance per offset change between the start of `from_year` and the end of `to_year`.
+///
+/// The transitions are looked up in the tz database instead of writing RRULEs, which keeps historic changes correct.
+pub fn timezone(timezone: Tz, from_year: i32, to_year: i32) -> ics::TimeZone<'static> {
+    let start = NaiveDate::from_ymd(from_year, 1, 1).and_hms(0, 0, 0);
+    let end = NaiveDate::from_ymd(to_year + 1, 1, 1).and_hms(0, 0, 0);
+
+    let mut current = Observance::at(timezone, &start);
+    let mut definition = ics::TimeZone::new(timezone.name(), current.to_zone_time(current.offset, start));
+
+    let mut day = start;
+    while day < end {
+        let next_day = day + Duration::days(1);
+        let observance = Observance::at(timezone, &next_day);
+
+        if observance != current {
+            // the offset changed during this day, find the exact second
+            let (mut before, mut after) = (day, next_day);
+            while after - before > Duration::seconds(1) {
+                let middle = before + (after - before) / 2;
+                if Observance::at(timezone, &middle) == current {
+                    before = middle;
+                } else {
+                    after = middle;
+                }
+            }
+
+            definition.add_zonetime(observance.to_zone_time(current.offset, after));
+            current = observance;
+        }
+        day = next_day;
+    }
+
+    definition
+}
+
+/// A VEVENT of an imported calendar, reduced to the properties which can be mapped onto an event.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParsedEvent {
+    pub uid: Option<String>,
+    pub summary: String,
+    pub description: String,
+    /// UTC
+    pub start: NaiveDateTime,
+    /// UTC
+    pub end: NaiveDateTime,
+    pub location: Option<String>,
+    pub categories: Vec<String>,
+}
+
+struct ContentLine {
+    number: usize,
+    name: String,
+    parameters: Vec<(String, String)>,
+    value: String,
+}
+
+impl ContentLine {
+    fn parameter(&self, name: &str) -> Option<&str> {
+        self.parameters
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("line {}: {}: {}", self.number, self.name, message)
+    }
+}
+
+/// Unfolds the content lines of an iCalendar file, `number` is the line the content line started at.
+fn content_lines(input: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+
+    for (index, line) in input.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        match (line.strip_prefix(|c| c == ' ' || c == '\t'), lines.last_mut()) {
+            (Some(continuation), Some((_, last))) => last.push_str(continuation),
+            _ if line.is_empty() => {}
+            _ => lines.push((index + 1, line.to_string())),
+        }
+    }
+
+    lines
+}
+
+fn parse_content_line(number: usize, line: &str) -> Result<ContentLine, String> {
+    let mut name = String::new();
+    let mut parameters = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c == ';' || c == ':' {
+            break;
+        }
+        name.push(c);
+        chars.next();
+    }
+
+    while chars.peek() == Some(&';') {
+        chars.next();
+        let mut key = String::new();
+        while let Some(c) = chars.next() {
+            if c == '=' {
+                break;
+            }
+            key.push(c);
+        }
+
+        let mut value = String::new();
+        let mut quoted = false;
+        while let Some(&c) = chars.peek() {
+            match c {
+                '"' => quoted = !quoted,
+                ';' | ':' if !quoted => break,
+                c => value.push(c),
+            }
+            chars.next();
+        }
+        parameters.push((key, value));
+    }
+
+    if chars.next() != Some(':') {
+        return Err(format!("line {}: expected a ':' after the property name", number));
+    }
+
+    Ok(ContentLine {
+        number,
+        name: name.to_ascii_uppercase(),
+        parameters,
+        value: chars.collect(),
+    })
+}
+
+/// Reverses `text`, with `split` values separated by unescaped commas are returned separately.
+fn unescape_values(value: &str, split: bool) -> Vec<String> {
+    let mut values = vec![String::new()];
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') | Some('N') => values.last_mut().unwrap().push('\n'),
+                Some(c) => values.last_mut().unwrap().push(c),
+                None => {}
+            },
+            ',' if split => values.push(String::new()),
+            c => values.last_mut().unwrap().push(c),
+        }
+    }
+
+    values
+}
+
+fn unescape(value: &str) -> String {
+    unescape_values(value, false).remove(0)
+}
+
+/// Parses DATE and DATE-TIME values into UTC, floating times and unknown TZIDs are read in `timezone`.
+///
+/// The flag is set for DATE values, which mark all-day events.
+fn parse_time(line: &ContentLine, timezone: Tz) -> Result<(NaiveDateTime, bool), String> {
+    let timezone = line
+        .parameter("TZID")
+        .and_then(|tzid| tzid.trim_start_matches('/').parse::<Tz>().ok())
+        .unwrap_or(timezone);
+    let to_utc = |local: NaiveDateTime| local_to_utc(timezone, local).ok_or_else(|| line.error("time out of range"));
+
+    let value = line.value.trim();
+    if line.parameter("VALUE").map_or(false, |value| value.eq_ignore_ascii_case("DATE")) || value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").map_err(|e| line.error(&e.to_string()))?;
+        return Ok((to_utc(date.and_hms(0, 0, 0))?, true));
+    }
+
+    match value.strip_suffix('Z') {
+        Some(utc) => NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S")
+            .map(|time| (time, false))
+            .map_err(|e| line.error(&e.to_string())),
+        None => NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+            .map_err(|e| line.error(&e.to_string()))
+            .and_then(to_utc)
+            .map(|time| (time, false)),
+    }
+}
+
+/// Longest accepted DURATION, about ten years.
+const MAX_DURATION_SECONDS: i64 = 3650 * 24 * 60 * 60;
+
+/// Parses durations like `PT1H30M`, `P1D` or `P2W`.
+fn parse_duration(line: &ContentLine) -> Result<Duration, String> {
+    let value = line.value.trim();
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(value) => (true, value),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let value = value.strip_prefix('P').ok_or_else(|| line.error("durations start with P"))?;
+
+    let mut seconds = 0i64;
+    let mut number = String::new();
+    let mut time = false;
+    for c in value.chars() {
+        match c {
+            'T' => time = true,
+            '0'..='9' => number.push(c),
+            unit => {
+                if number.is_empty() {
+                    return Err(line.error("missing number in duration"));
+                }
+                let unit_seconds = match (unit, time) {
+                    ('W', false) => 7 * 24 * 60 * 60,
+                    ('D', false) => 24 * 60 * 60,
+                    ('H', true) => 60 * 60,
+                    ('M', true) => 60,
+                    ('S', true) => 1,
+                    _ => return Err(line.error("unknown unit in duration")),
+                };
+                seconds = number
+                    .parse::<i64>()
+                    .ok()
+                    .and_then(|amount| amount.checked_mul(unit_seconds))
+                    .and_then(|amount| seconds.checked_add(amount))
+                    .filter(|seconds| *seconds <= MAX_DURATION_SECONDS)
+                    .ok_or_else(|| line.error("duration out of range"))?;
+                number.clear();
+            }
+        }
+    }
+
+    Ok(Duration::seconds(if negative { -seconds } else { seconds }))
+}
+
+/// Reads all VEVENTs of an iCalendar file.
+///
+/// Events without DTEND end after their DURATION, after one day for all-day events and otherwise when they start.
+pub fn parse(input: &str, timezone: Tz) -> Result<Vec<ParsedEvent>, String> {
+    let mut events = Vec::new();
+    let mut properties: Option<Vec<ContentLine>> = None;
+    // components nested into the current VEVENT, like VALARM
+    let mut depth = 0;
+
+    for (number, line) in content_lines(input) {
+        let line = parse_content_line(number, &line)?;
+        let component = line.value.trim().to_ascii_uppercase();
+
+        match (line.name.as_str(), properties.as_mut()) {
+            ("BEGIN", None) if component == "VEVENT" => properties = Some(Vec::new()),
+            ("BEGIN", Some(_)) => depth += 1,
+            ("END", Some(_)) if depth > 0 => depth -= 1,
+            ("END", Some(_)) if component == "VEVENT" => {
+                events.push(parse_event(properties.take().unwrap_or_default(), number, timezone)?);
+            }
+            (_, Some(properties)) if depth == 0 => properties.push(line),
+            _ => {}
+        }
+    }
+
+    if properties.is_some() {
+        return Err("the last VEVENT isn't terminated by END:VEVENT".to_string());
+    }
+
+    Ok(events)
+}
+
+fn parse_event(properties: Vec<ContentLine>, end_line: usize, timezone: Tz) -> Result<ParsedEvent, String> {
+    let mut uid = None;
+    let mut summary = String::new();
+    let mut description = String::new();
+    let mut location = None;
+    let mut categories = Vec::new();
+    let mut start = None;
+    let mut end = None;
+    let mut duration = None;
+
+    for property in properties {
+        match property.name.as_str() {
+            "UID" => uid = Some(property.value.trim().to_string()),
+            "SUMMARY" => summary = unescape(&property.value),
+            "DESCRIPTION" => description = unescape(&property.value),
+            "LOCATION" => location = Some(unescape(&property.value)).filter(|location| !location.trim().is_empty()),
+            "CATEGORIES" => categories.extend(unescape_values(&property.value, true)
+                .into_iter()
+                .map(|category| category.trim().to_string())
+                .filter(|category| !category.is_empty())),
+            "DTSTART" => start = Some(parse_time(&property, timezone)?),
+            "DTEND" => end = Some(parse_time(&property, timezone)?.0),
+            "DURATION" => duration = Some(property),
+            _ => {}
+        }
+    }
+
+    let (start, all_day) = start.ok_or_else(|| format!("line {}: VEVENT without DTSTART", end_line))?;
+    let end = match (end, duration) {
+        (Some(end), _) => end,
+        (None, Some(duration)) => start
+            .checked_add_signed(parse_duration(&duration)?)
+            .ok_or_else(|| duration.error("duration out of range"))?,
+        (None, None) if all_day => start
+            .checked_add_signed(Duration::days(1))
+            .ok_or_else(|| format!("line {}: DTSTART: date out of range", end_line))?,
+        (None, None) => start,
+    };
+
+    Ok(ParsedEvent {
+        uid,
+        summary,
+        description,
+        start,
+        end,
+        location,
+        categories,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveDateTime};
+    use chrono_tz::Tz;
+
+    use super::{local_to_utc, parse};
+
+    fn calendar(event: &str) -> String {
+        format!("BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\n{}END:VEVENT\r\nEND:VCALENDAR\r\n", event)
+    }
+
+    fn time(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd(y, m, d).and_hms(h, min, 0)
+    }
+
+    #[test]
+    fn local_to_utc_at_the_limits() {
+        let min = chrono::naive::MIN_DATE.and_hms(0, 0, 0);
+        let max = chrono::naive::MAX_DATE.and_hms(23, 59, 59);
+
+        assert_eq!(local_to_utc(Tz::Europe__Berlin, min), None);
+        assert_eq!(local_to_utc(Tz::America__New_York, max), None);
+        assert_eq!(local_to_utc(Tz::Europe__Berlin, time(2021, 7, 1, 20, 0)), Some(time(2021, 7, 1, 18, 0)));
+        // skipped by the change to summer time
+        assert_eq!(local_to_utc(Tz::Europe__Berlin, time(2021, 3, 28, 2, 30)), Some(time(2021, 3, 28, 1, 30)));
+    }
+
+    #[test]
+    fn unfolds_lines() {
+        let input = calendar("DTSTART:20210701T200000Z\r\nSUMMARY:A long\r\n  summary\r\nDESCRIPTION:First\\nsec\r\n\tond\r\n");
+        let events = parse(&input, Tz::UTC).unwrap();
+
+        assert_eq!(events[0].summary, "A long summary");
+        assert_eq!(events[0].description, "First\nsecond");
+    }
+
+    #[test]
+    fn converts_times_to_utc() {
+        let input = calendar("DTSTART;TZID=Europe/Berlin:20210701T200000\r\nDTEND:20210701T230000Z\r\n");
+        let event = &parse(&input, Tz::UTC).unwrap()[0];
+
+        assert_eq!(event.start, time(2021, 7, 1, 18, 0));
+        assert_eq!(event.end, time(2021, 7, 1, 23, 0));
+    }
+
+    #[test]
+    fn reads_floating_times_in_the_given_timezone() {
+        let input = calendar("DTSTART:20210115T200000\r\n");
+        let event = &parse(&input, Tz::Europe__Berlin).unwrap()[0];
+
+        assert_eq!(event.start, time(2021, 1, 15, 19, 0));
+        assert_eq!(event.end, event.start);
+    }
+
+    #[test]
+    fn all_day_events_last_a_day() {
+        let input = calendar("DTSTART;VALUE=DATE:20210701\r\n");
+        let event = &parse(&input, Tz::Europe__Berlin).unwrap()[0];
+
+        assert_eq!(event.start, time(2021, 6, 30, 22, 0));
+        assert_eq!(event.end, time(2021, 7, 1, 22, 0));
+    }
+
+    #[test]
+    fn ends_after_the_duration() {
+        let input = calendar("DTSTART:20210701T200000Z\r\nDURATION:PT1H30M\r\n");
+        let event = &parse(&input, Tz::UTC).unwrap()[0];
+
+        assert_eq!(event.end, time(2021, 7, 1, 21, 30));
+    }
+
+    #[test]
+    fn prefers_dtend_over_the_duration() {
+        let input = calendar("DTSTART:20210701T200000Z\r\nDURATION:P1W\r\nDTEND:20210701T220000Z\r\n");
+        let event = &parse(&input, Tz::UTC).unwrap()[0];
+
+        assert_eq!(event.end, time(2021, 7, 1, 22, 0));
+    }
+
+    #[test]
+    fn rejects_durations_out_of_range() {
+        let input = calendar("DTSTART:20210701T200000Z\r\nDURATION:P99999999999999W\r\n");
+
+        assert_eq!(parse(&input, Tz::UTC).unwrap_err(), "line 5: DURATION: duration out of range");
+    }
+
+    #[test]
+    fn ignores_properties_of_nested_components() {
+        let input = calendar(
+            "DTSTART:20210701T200000Z\r\nDESCRIPTION:Event\r\n\
+             BEGIN:VALARM\r\nTRIGGER:-PT15M\r\nDESCRIPTION:Alarm\r\nDURATION:PT5M\r\nEND:VALARM\r\n\
+             CATEGORIES:music,talk\r\n"
+        );
+        let events = parse(&input, Tz::UTC).unwrap();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].description, "Event");
+        assert_eq!(events[0].end, events[0].start);
+        assert_eq!(events[0].categories, vec!["music", "talk"]);
+    }
+
+    #[test]
+    fn rejects_unterminated_events() {
+        let input = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nDTSTART:20210701T200000Z\r\n";
+
+        assert!(parse(input, Tz::UTC).is_err());
+    }
+}
@@ -0,0 +1,73 @@
+use std::fmt;
+use std::io;
+
+use rocket::http::Status;
+use rocket::Request;
+use rocket::response::{self, Responder};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// the backend doesn't know the requested entity
+    NotFound,
+    /// the backend couldn't be reached or didn't answer in time
+    BackendUnavailable(String),
+    /// the backend answered with errors (with or without data), contains all of them including their paths and locations
+    GraphQL(Vec<graphql_client::Error>),
+    /// the backend answered with something we can't read
+    Decode(String),
+    Io(io::Error),
+}
+
+impl Error {
+    pub fn status(&self) -> Status {
+        match self {
+            Error::NotFound => Status::NotFound,
+            Error::BackendUnavailable(_) => Status::ServiceUnavailable,
+            Error::GraphQL(_) | Error::Decode(_) | Error::Io(_) => Status::InternalServerError,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotFound => write!(f, "not found"),
+            Error::BackendUnavailable(reason) => write!(f, "backend unavailable: {}", reason),
+            Error::GraphQL(errors) => write!(f, "backend returned errors: {}", errors
+                .iter()
+                .map(|error| error.to_string())
+                .collect::<Vec<String>>()
+                .join(" | ")),
+            Error::Decode(reason) => write!(f, "couldn't decode backend response: {}", reason),
+            Error::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Failed requests, decoding a response body has to be mapped to `Error::Decode` explicitly.
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::BackendUnavailable(e.to_string())
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// Forwards to the catcher of the matching status, which renders the error page.
+impl<'r> Responder<'r> for Error {
+    fn respond_to(self, _: &Request) -> response::Result<'r> {
+        if self.status() != Status::NotFound {
+            eprintln!("Error while handling request: {}", self);
+        }
+
+        Err(self.status())
+    }
+}
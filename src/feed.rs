@@ -0,0 +1,90 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use crate::search::escape_html;
+
+/// An entry of an RSS or Atom feed, `content` is HTML.
+#[derive(Debug)]
+pub struct FeedItem {
+    pub title: String,
+    pub link: String,
+    pub content: String,
+    /// UTC
+    pub published: NaiveDateTime,
+    pub categories: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Feed {
+    pub title: String,
+    /// the HTML page the feed belongs to
+    pub link: String,
+    /// the url of the feed itself
+    pub self_link: String,
+    pub items: Vec<FeedItem>,
+}
+
+impl Feed {
+    /// Time of the newest item, or now for an empty feed.
+    fn updated(&self) -> DateTime<Utc> {
+        self.items
+            .iter()
+            .map(|item| item.published)
+            .max()
+            .map_or_else(Utc::now, |time| DateTime::from_utc(time, Utc))
+    }
+
+    pub fn to_rss(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str("<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\">\n<channel>\n");
+        xml.push_str(&format!("<title>{}</title>\n", escape_html(&self.title)));
+        xml.push_str(&format!("<link>{}</link>\n", escape_html(&self.link)));
+        xml.push_str(&format!("<description>{}</description>\n", escape_html(&self.title)));
+        xml.push_str(&format!("<atom:link href=\"{}\" rel=\"self\" type=\"application/rss+xml\"/>\n", escape_html(&self.self_link)));
+        xml.push_str(&format!("<lastBuildDate>{}</lastBuildDate>\n", self.updated().to_rfc2822()));
+
+        for item in &self.items {
+            xml.push_str("<item>\n");
+            xml.push_str(&format!("<title>{}</title>\n", escape_html(&item.title)));
+            xml.push_str(&format!("<link>{}</link>\n", escape_html(&item.link)));
+            xml.push_str(&format!("<guid isPermaLink=\"true\">{}</guid>\n", escape_html(&item.link)));
+            xml.push_str(&format!("<pubDate>{}</pubDate>\n", DateTime::<Utc>::from_utc(item.published, Utc).to_rfc2822()));
+            for category in &item.categories {
+                xml.push_str(&format!("<category>{}</category>\n", escape_html(category)));
+            }
+            xml.push_str(&format!("<description>{}</description>\n", escape_html(&item.content)));
+            xml.push_str("</item>\n");
+        }
+
+        xml.push_str("</channel>\n</rss>\n");
+        xml
+    }
+
+    pub fn to_atom(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+        xml.push_str(&format!("<id>{}</id>\n", escape_html(&self.self_link)));
+        xml.push_str(&format!("<title>{}</title>\n", escape_html(&self.title)));
+        xml.push_str(&format!("<updated>{}</updated>\n", self.updated().to_rfc3339()));
+        xml.push_str(&format!("<link href=\"{}\"/>\n", escape_html(&self.link)));
+        xml.push_str(&format!("<link href=\"{}\" rel=\"self\" type=\"application/atom+xml\"/>\n", escape_html(&self.self_link)));
+        xml.push_str(&format!("<author><name>{}</name></author>\n", escape_html(&self.title)));
+
+        for item in &self.items {
+            let published = DateTime::<Utc>::from_utc(item.published, Utc).to_rfc3339();
+            xml.push_str("<entry>\n");
+            xml.push_str(&format!("<id>{}</id>\n", escape_html(&item.link)));
+            xml.push_str(&format!("<title>{}</title>\n", escape_html(&item.title)));
+            xml.push_str(&format!("<link href=\"{}\"/>\n", escape_html(&item.link)));
+            xml.push_str(&format!("<published>{}</published>\n", published));
+            xml.push_str(&format!("<updated>{}</updated>\n", published));
+            for category in &item.categories {
+                xml.push_str(&format!("<category term=\"{}\"/>\n", escape_html(category)));
+            }
+            xml.push_str(&format!("<content type=\"html\">{}</content>\n", escape_html(&item.content)));
+            xml.push_str("</entry>\n");
+        }
+
+        xml.push_str("</feed>\n");
+        xml
+    }
+}
@@ -1,9 +1,9 @@
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
 use rocket_contrib::templates::handlebars::{Context, Handlebars, Helper, HelperResult, JsonValue, Output, RenderContext};
 use rocket_contrib::templates::handlebars::JsonRender;
-use rocket_contrib::templates::handlebars::template::{Parameter, TemplateElement};
 
-use crate::{get_pagination, PaginationContext};
+use crate::markdown;
 
 pub fn helper_add(h: &Helper, _: &Handlebars, _: &Context, _: &mut RenderContext, out: &mut dyn Output) -> HelperResult {
     out.write(JsonValue::from(
@@ -13,139 +13,7 @@ pub fn helper_add(h: &Helper, _: &Handlebars, _: &Context, _: &mut RenderContext
     Ok(())
 }
 
-pub fn helper_previous_navigation(h: &Helper, _: &Handlebars, context: &Context, rc: &mut RenderContext, out: &mut dyn Output) -> HelperResult {
-    let default = serde_json::Map::new();
-    let page_id = context
-        .data()
-        .as_object()
-        .unwrap_or(&default)
-        .get("page_id")
-        .and_then(|x|
-            x.as_i64())
-        .unwrap_or(1i64);
-    let link_prefix = if let Some(param) = h.param(0) {
-        param.value().as_str().unwrap_or("")
-    } else {
-        ""
-    };
-
-    let value = if page_id != 1 {
-        format!(r#"<li class="page-item background-secondary">
-                    <a class="page-link text-color background-secondary" href="/{}{}"
-                       aria-label="Previous">
-                        <span aria-hidden="true">&laquo;</span>
-                        <span class="sr-only">Previous</span>
-                    </a>
-                </li>
-                <li class="page-item background-secondary"><a class="page-link text-color background-secondary"
-                                                              href="/{}{}">{}</a>
-                </li>"#, link_prefix, page_id - 1, link_prefix, page_id - 1, page_id - 1)
-    } else {
-        String::new()
-    };
-
-    out.write(JsonValue::String(value).render().as_ref())?;
-    Ok(())
-}
-
-// TODO: actually implement this
-// we need a max_page for this
-pub fn helper_next_navigation(h: &Helper, _: &Handlebars, context: &Context, rc: &mut RenderContext, out: &mut dyn Output) -> HelperResult {
-    let default = serde_json::Map::new();
-    let context = context
-        .data()
-        .as_object()
-        .unwrap_or(&default);
-
-    let mut value = match h.template() {
-        None => {
-            // this never happens unless the user forgets to add an inline template inside next, IDC
-            String::new()
-        }
-        Some(template) => {
-            template
-                .elements
-                .clone()
-                .into_iter()
-                .map(|element|
-                    match element {
-                        TemplateElement::RawString(string) => {
-                            string
-                        }
-                        TemplateElement::HelperExpression(expression) => {
-                            match expression.name.as_ref() {
-                                "add" => {
-                                    // TODO: throw error
-                                    let first_param = expression.params.get(0).unwrap();
-                                    let second_param = expression.params.get(1).unwrap();
-
-                                    let x = match first_param {
-                                        Parameter::Name(name) => {
-                                            // TODO: throw error
-                                            context.get(name.as_str()).unwrap_or(&JsonValue::from(0i64)).as_i64().unwrap()
-                                        }
-                                        Parameter::Literal(x) => {
-                                            x.as_i64().unwrap()
-                                        }
-                                        _ => {
-                                            // TODO: throw error
-                                            0i64
-                                        }
-                                    };
-
-                                    let y = match second_param {
-                                        Parameter::Name(name) => {
-                                            // TODO: throw error
-                                            context.get(name.as_str()).unwrap_or(&JsonValue::from(0i64)).as_i64().unwrap()
-                                        }
-                                        Parameter::Literal(x) => {
-                                            x.as_i64().unwrap()
-                                        }
-                                        _ => {
-                                            // TODO: throw error
-                                            0i64
-                                        }
-                                    };
-                                    format!("{}", x + y)
-                                }
-                                _ => String::new()
-                            }
-                        }
-                        _ => String::new(),
-                    }
-                )
-                .collect::<Vec<String>>()
-                .join("\n")
-        }
-    };
-
-    let page_id = context
-        .get("page_id")
-        .and_then(|x|
-            x.as_i64())
-        .unwrap_or(1i64);
-    let pagination_context = h
-        .param(0)
-        .and_then(|param|
-            param.value().as_str())
-        .unwrap_or("events");
-    let events_count = get_pagination()
-        .and_then(|x|
-            Ok(match pagination_context {
-                "locations" => x.location_count,
-                "organizers" => x.organizer_count,
-                _ => x.event_count
-            }))
-        .unwrap_or(PaginationContext::default().limit as i64);
-
-    if page_id >= (events_count / PaginationContext::default().limit as i64) {
-        value = String::new();
-    }
-
-    out.write(JsonValue::String(value).render().as_ref())?;
-    Ok(())
-}
-
+/// Formats a UTC time in the timezone named by the `timezone` field of the template context (UTC if it's missing).
 pub fn helper_time_custom_format(h: &Helper, _: &Handlebars, context: &Context, _: &mut RenderContext, out: &mut dyn Output) -> HelperResult {
     let param = h.param(0).unwrap();
     let format_param = h.param(1).unwrap();
@@ -153,26 +21,23 @@ pub fn helper_time_custom_format(h: &Helper, _: &Handlebars, context: &Context,
     let value = param.value().as_str().unwrap();
     let format_string = format_param.value().as_str().unwrap();
     let value = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S").unwrap(); // TODO
+    let timezone = context
+        .data()
+        .get("timezone")
+        .and_then(|timezone| timezone.as_str())
+        .and_then(|timezone| timezone.parse::<Tz>().ok())
+        .unwrap_or(Tz::UTC);
 
-    out.write(JsonValue::String(value.format(format_string).to_string()).render().as_ref())?;
+    out.write(JsonValue::String(timezone.from_utc_datetime(&value).format(format_string).to_string()).render().as_ref())?;
     Ok(())
 }
 
-pub fn helper_format_description(h: &Helper, _: &Handlebars, _: &Context, _: &mut RenderContext, out: &mut dyn Output) -> HelperResult {
+/// Renders the Markdown of a description, see `markdown::render`.
+pub fn helper_markdown(h: &Helper, _: &Handlebars, _: &Context, _: &mut RenderContext, out: &mut dyn Output) -> HelperResult {
     let param = h.param(0).unwrap();
-    let description = param.value().as_str().unwrap();
-
-    let result = (description.to_string() + " http://carstens.tech").split_whitespace().map(|element| {
-        if element.starts_with("http") {
-            format!("<a href='{}' class='description-link'>{}</a>", element, element)
-        } else {
-            element.to_string()
-        }
-    })
-        .collect::<Vec<String>>()
-        .join(" ");
+    let description = param.value().as_str().unwrap_or_default();
 
-    out.write(JsonValue::String(result).render().as_ref())?;
+    out.write(&markdown::render(description))?;
     Ok(())
 }
 
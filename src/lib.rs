@@ -1,19 +1,43 @@
 extern crate chrono;
+extern crate chrono_tz;
 extern crate graphql_client;
+extern crate ics;
+extern crate image;
+extern crate pulldown_cmark;
 extern crate rocket_contrib;
+extern crate rusttype;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
-#[macro_use]
 extern crate serde_json;
 
-use std::env::VarError;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use graphql_client::{GraphQLQuery, QueryBody, Response};
+use rocket::{Config, Request, State, request};
+use rocket::request::FromRequest;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::cache::ResponseCache;
+use crate::error::Error;
 
 pub use helper::*;
 pub use pagination::*;
 
+pub mod cache;
+pub mod calendar;
+pub mod error;
+pub mod feed;
 pub mod helper;
+pub mod markdown;
 pub mod pagination;
+pub mod period;
+pub mod search;
+pub mod share_image;
 
 pub fn backend_url() -> String {
     match std::env::var("BACKEND_URL") {
@@ -21,7 +45,7 @@ pub fn backend_url() -> String {
             var
         }
         Err(_) => {
-            if std::env::var("ROCKET_ENV").unwrap_or("dev".to_string()).starts_with("prod") {
+            if std::env::var("ROCKET_ENV").unwrap_or_else(|_| "dev".to_string()).starts_with("prod") {
                 "http://event-manager/graphql"
             } else {
                 "http://localhost:8001/graphql"
@@ -29,3 +53,297 @@ pub fn backend_url() -> String {
         }.to_string()
     }
 }
+
+/// Settings describing the public site, taken from the rocket config.
+#[derive(Clone, Debug)]
+pub struct Site {
+    /// e.g. `https://events.example.org`, without a trailing slash
+    pub base_url: String,
+    /// the domain part of the UIDs in ICS files, defaults to the host of `base_url`
+    pub uid_domain: String,
+    pub timezone: Tz,
+}
+
+impl Site {
+    /// Uses the `base_url`, `uid_domain` and `timezone` settings of the rocket config.
+    pub fn from_config(config: &Config) -> std::result::Result<Site, String> {
+        let base_url = config
+            .get_string("base_url")
+            .unwrap_or_else(|_| "http://localhost:8000".to_string())
+            .trim_end_matches('/')
+            .to_string();
+        let uid_domain = match config.get_string("uid_domain") {
+            Ok(domain) => domain,
+            Err(_) => reqwest::Url::parse(&base_url)
+                .map_err(|e| format!("invalid base_url {:?}: {}", base_url, e))?
+                .host_str()
+                .ok_or_else(|| format!("base_url {:?} has no host", base_url))?
+                .to_string(),
+        };
+        let timezone = config
+            .get_str("timezone")
+            .unwrap_or("Europe/Berlin")
+            .parse()
+            .map_err(|e| format!("invalid timezone: {}", e))?;
+
+        Ok(Site {
+            base_url,
+            uid_domain,
+            timezone,
+        })
+    }
+
+    /// The absolute url of a path like `/event/1`.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+}
+
+/// Countries which only use a single timezone, to guess it from the region of an `Accept-Language` entry.
+const REGION_TIMEZONES: [(&str, Tz); 22] = [
+    ("AT", Tz::Europe__Vienna),
+    ("BE", Tz::Europe__Brussels),
+    ("CH", Tz::Europe__Zurich),
+    ("CZ", Tz::Europe__Prague),
+    ("DE", Tz::Europe__Berlin),
+    ("DK", Tz::Europe__Copenhagen),
+    ("ES", Tz::Europe__Madrid),
+    ("FI", Tz::Europe__Helsinki),
+    ("FR", Tz::Europe__Paris),
+    ("GB", Tz::Europe__London),
+    ("GR", Tz::Europe__Athens),
+    ("HU", Tz::Europe__Budapest),
+    ("IE", Tz::Europe__Dublin),
+    ("IT", Tz::Europe__Rome),
+    ("JP", Tz::Asia__Tokyo),
+    ("LU", Tz::Europe__Luxembourg),
+    ("NL", Tz::Europe__Amsterdam),
+    ("NO", Tz::Europe__Oslo),
+    ("PL", Tz::Europe__Warsaw),
+    ("PT", Tz::Europe__Lisbon),
+    ("SE", Tz::Europe__Stockholm),
+    ("UK", Tz::Europe__London),
+];
+
+/// The timezone times are shown in for the current visitor.
+///
+/// Taken from the `timezone` cookie, guessed from the `Accept-Language` header or the one of the site.
+#[derive(Clone, Copy, Debug)]
+pub struct VisitorTimezone(pub Tz);
+
+impl VisitorTimezone {
+    pub const COOKIE: &'static str = "timezone";
+
+    pub fn name(&self) -> String {
+        self.0.name().to_string()
+    }
+
+    fn from_accept_language(header: &str) -> Option<Tz> {
+        header
+            .split(',')
+            .filter_map(|language| language.split(';').next())
+            .filter_map(|language| language.trim().split(|c| c == '-' || c == '_').nth(1))
+            .find_map(|region| REGION_TIMEZONES
+                .iter()
+                .find(|(code, _)| code.eq_ignore_ascii_case(region))
+                .map(|(_, timezone)| *timezone))
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for VisitorTimezone {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<VisitorTimezone, ()> {
+        request
+            .guard::<State<Site>>()
+            .map(|site| VisitorTimezone(request
+                .cookies()
+                .get(VisitorTimezone::COOKIE)
+                .and_then(|cookie| cookie.value().parse().ok())
+                .or_else(|| request.headers().get_one("Accept-Language").and_then(VisitorTimezone::from_accept_language))
+                .unwrap_or(site.timezone)))
+    }
+}
+
+/// Metadata of a page used for the canonical link and the Open Graph/Twitter card tags.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PageMeta {
+    /// absolute url of the page, without the query
+    pub url: String,
+    pub description: Option<String>,
+    /// absolute url of the share image
+    pub image: String,
+    /// whether `image` is a share image made for the page, which is shown as a large card
+    pub large_image: bool,
+}
+
+impl PageMeta {
+    /// Maximum length of a description, longer ones are cut at a word boundary.
+    const DESCRIPTION_LENGTH: usize = 200;
+
+    /// Uses the first `DESCRIPTION_LENGTH` characters of `text` with collapsed whitespace as the description.
+    pub fn describe(mut self, text: &str) -> PageMeta {
+        let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+        if text.is_empty() {
+            return self;
+        }
+
+        self.description = Some(if text.chars().count() <= PageMeta::DESCRIPTION_LENGTH {
+            text
+        } else {
+            let cut = text.chars().take(PageMeta::DESCRIPTION_LENGTH).collect::<String>();
+            let cut = cut.rsplitn(2, ' ').last().unwrap_or(&cut);
+            format!("{}…", cut.trim_end_matches(|c: char| c.is_ascii_punctuation()))
+        });
+
+        self
+    }
+
+    pub fn with_image(mut self, url: String) -> PageMeta {
+        self.image = url;
+        self.large_image = true;
+        self
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for PageMeta {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<PageMeta, ()> {
+        request
+            .guard::<State<Site>>()
+            .map(|site| PageMeta {
+                url: site.url(request.uri().path()),
+                description: None,
+                image: site.url("/public/header.png"),
+                large_image: false,
+            })
+    }
+}
+
+/// Sends GraphQL operations to the backend.
+///
+/// Wraps a single pooled `reqwest` client and the response cache, it's cheap to clone and meant to live in Rocket's managed state.
+#[derive(Clone, Debug)]
+pub struct GraphQLClient {
+    client: reqwest::blocking::Client,
+    url: String,
+    cache: Arc<ResponseCache>,
+    /// when the oldest cached response served instead of the unreachable backend was fetched
+    stale_since: Arc<Mutex<Option<DateTime<Utc>>>>,
+}
+
+impl GraphQLClient {
+    pub fn new(url: String, timeout: Duration, connect_timeout: Duration, cache: ResponseCache) -> error::Result<GraphQLClient> {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(timeout)
+            .connect_timeout(connect_timeout)
+            .build()?;
+
+        Ok(GraphQLClient {
+            client,
+            url,
+            cache: Arc::new(cache),
+            stale_since: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// Uses `backend_url()` and the `backend_timeout`/`backend_connect_timeout` settings (in seconds) of the rocket config.
+    pub fn from_config(config: &Config) -> error::Result<GraphQLClient> {
+        let seconds = |name, default| Duration::from_secs(config.get_int(name).map(|value| value as u64).unwrap_or(default));
+
+        GraphQLClient::new(
+            backend_url(),
+            seconds("backend_timeout", 10),
+            seconds("backend_connect_timeout", 5),
+            ResponseCache::from_config(config),
+        )
+    }
+
+    /// Shares the connection pool and cache, but tracks stale responses separately.
+    pub fn for_request(&self) -> GraphQLClient {
+        GraphQLClient {
+            stale_since: Arc::new(Mutex::new(None)),
+            ..self.clone()
+        }
+    }
+
+    /// Set if at least one response was served from the cache because the backend was unavailable.
+    pub fn stale_since(&self) -> Option<DateTime<Utc>> {
+        *self.stale_since.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Runs a query, answering from the cache while the last response for the same variables is fresh.
+    ///
+    /// Every response is stored, also for operations whose ttl is 0.
+    ///
+    /// If the backend is unavailable the last response is used regardless of its age, see `stale_since`.
+    pub fn query<Q: GraphQLQuery>(&self, variables: Q::Variables) -> error::Result<Q::ResponseData> {
+        let body = Q::build_query(variables);
+        let variables = serde_json::to_string(&body.variables).map_err(|e| Error::Decode(e.to_string()))?;
+        let key = ResponseCache::key(body.operation_name, &variables);
+        if let Some(data) = self.cache.get(body.operation_name, &key) {
+            return decode(data);
+        }
+
+        let data = match self.send(&body) {
+            Ok(data) => data,
+            Err(Error::BackendUnavailable(reason)) => {
+                let (data, fetched) = self.cache.get_stale(&key).ok_or(Error::BackendUnavailable(reason))?;
+                let mut stale_since = self.stale_since.lock().unwrap_or_else(PoisonError::into_inner);
+                *stale_since = Some(stale_since.map_or(fetched, |since| since.min(fetched)));
+
+                return decode(data);
+            }
+            Err(e) => return Err(e),
+        };
+        self.cache.insert(key, data.clone());
+
+        decode(data)
+    }
+
+    /// Runs a mutation, which is never cached and invalidates all cached responses.
+    pub fn mutate<Q: GraphQLQuery>(&self, variables: Q::Variables) -> error::Result<Q::ResponseData> {
+        let data = self.send(&Q::build_query(variables))?;
+        self.cache.invalidate();
+
+        decode(data)
+    }
+
+    /// Server errors (like the 502 of a proxy while the backend restarts) count as the backend being unavailable.
+    fn send<V: Serialize>(&self, body: &QueryBody<V>) -> error::Result<serde_json::Value> {
+        let response = self.client
+            .post(&self.url)
+            .json(body)
+            .send()?;
+        if response.status().is_server_error() {
+            return Err(Error::BackendUnavailable(format!("backend answered with {}", response.status())));
+        }
+
+        let response: Response<serde_json::Value> = response
+            .json()
+            .map_err(|e| Error::Decode(e.to_string()))?;
+
+        // partial data with errors (like `{"updateEvent": null}`) is treated as a failure, the errors tell what went wrong
+        match (response.data, response.errors) {
+            (_, Some(errors)) if !errors.is_empty() => Err(Error::GraphQL(errors)),
+            (Some(data), _) => Ok(data),
+            (None, _) => Err(Error::Decode("response without data or errors".to_string())),
+        }
+    }
+}
+
+/// Every request gets its own view of the managed client, to know whether its page shows stale data.
+impl<'a, 'r> FromRequest<'a, 'r> for GraphQLClient {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<GraphQLClient, ()> {
+        request
+            .guard::<State<GraphQLClient>>()
+            .map(|client| client.for_request())
+    }
+}
+
+fn decode<T: DeserializeOwned>(data: serde_json::Value) -> error::Result<T> {
+    serde_json::from_value(data).map_err(|e| Error::Decode(e.to_string()))
+}
@@ -1,6 +1,7 @@
 #![feature(decl_macro, proc_macro_hygiene)]
 
 extern crate chrono;
+extern crate chrono_tz;
 extern crate events_frontend;
 extern crate graphql_client;
 extern crate ics;
@@ -11,31 +12,37 @@ extern crate rocket_contrib;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
-#[macro_use]
 extern crate serde_json;
-extern crate uuid;
+
+mod api;
 
 use std::cmp::max;
 use std::collections::HashMap;
-use std::io;
+use std::convert::TryFrom;
 use std::io::Cursor;
 use std::net::IpAddr;
-
-use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime};
-use graphql_client::{GraphQLQuery, Response};
-use ics::properties::{Class, Created, Description, DtEnd, DtStart, Status, Summary, URL};
-use rocket::{Request, request};
-use rocket::request::{FlashMessage, Form, FromRequest, Outcome};
-use rocket::response::{Content, Flash, Redirect, Stream};
+use std::ops::RangeInclusive;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use graphql_client::GraphQLQuery;
+use ics::parameters::{CN, TzIDParam};
+use ics::properties::{Categories, Class, Description, DtEnd, DtStart, Geo, Method, Status, Summary, URL};
+use rocket::{Request, State, http, request};
+use rocket::http::{Cookie, Cookies, RawStr};
+use rocket::request::{FlashMessage, Form, FormItems, FromForm, FromParam, FromRequest};
+use rocket::response::{self, Content, Flash, Redirect, Responder, Response, Stream};
 use rocket_contrib::serve::StaticFiles;
-use rocket_contrib::templates::handlebars::{Context, Handlebars, Helper, HelperResult, JsonRender, JsonValue, Output, Renderable, RenderContext, RenderError};
-use rocket_contrib::templates::handlebars::template::{HelperTemplate, Parameter, TemplateElement};
+use rocket_contrib::json::Json;
 use rocket_contrib::templates::Template;
 
-use events_frontend::{backend_url, PaginationContext};
+use events_frontend::feed::{Feed, FeedItem};
+use events_frontend::period::Period;
+use events_frontend::{calendar, get_pagination, markdown, search, share_image, GraphQLClient, PageMeta, PageSizes, PaginationContext, Paginator, PerPage, Site, VisitorTimezone};
+use events_frontend::error::{self, Error};
 use events_frontend::helper::*;
 
-#[derive(Clone, Debug, Deserialize, GraphQLQuery, Serialize)]
+#[derive(Clone, Debug, Default, Deserialize, GraphQLQuery, Serialize)]
 #[graphql(
 schema_path = "resources/schema.graphql",
 query_path = "resources/location.graphql",
@@ -54,7 +61,7 @@ struct Location {
     maps_link: String,
 }
 
-#[derive(Clone, Debug, Deserialize, FromForm, GraphQLQuery, Serialize)]
+#[derive(Clone, Debug, Deserialize, GraphQLQuery, Serialize)]
 #[graphql(
 schema_path = "resources/schema.graphql",
 query_path = "resources/mutations/location.graphql",
@@ -72,67 +79,537 @@ struct LocationMutation {
     maps_link: String,
 }
 
+/// Raw location form input, kept as strings so that invalid values can be shown again.
+#[derive(Clone, Debug, Default, Deserialize, FromForm, Serialize)]
+struct LocationForm {
+    name: String,
+    website: String,
+    street: String,
+    street_number: String,
+    city: String,
+    postal_code: String,
+    country: String,
+    building: String,
+    maps_link: String,
+}
+
+type FieldErrors = HashMap<String, String>;
+
+/// Number of digits of a postal code in the given country, `None` if we don't know the country.
+fn postal_code_length(country: &str) -> Option<usize> {
+    match country.trim().to_lowercase().as_str() {
+        "de" | "germany" | "deutschland" | "fr" | "france" | "it" | "italy" | "italia" | "es" | "spain"
+        | "fi" | "finland" | "us" | "usa" | "united states" => Some(5),
+        "at" | "austria" | "österreich" | "ch" | "switzerland" | "schweiz" | "be" | "belgium" | "belgien"
+        | "dk" | "denmark" | "dänemark" | "no" | "norway" | "lu" | "luxembourg" | "luxemburg" => Some(4),
+        _ => None,
+    }
+}
+
+fn is_http_url(value: &str) -> bool {
+    reqwest::Url::parse(value)
+        .map(|url| (url.scheme() == "http" || url.scheme() == "https") && url.host().is_some())
+        .unwrap_or(false)
+}
+
+impl LocationForm {
+    fn validate(&self) -> Result<LocationMutation, FieldErrors> {
+        let mut errors = FieldErrors::new();
+        let optional = |value: &str| Some(value.trim().to_string()).filter(|value| !value.is_empty());
+
+        for (field, value) in [
+            ("name", &self.name),
+            ("street", &self.street),
+            ("street_number", &self.street_number),
+            ("city", &self.city),
+            ("postal_code", &self.postal_code),
+            ("country", &self.country),
+            ("maps_link", &self.maps_link),
+        ].iter() {
+            if value.trim().is_empty() {
+                errors.insert(field.to_string(), "This field is required".to_string());
+            }
+        }
+
+        // the backend stores both numbers as 32 bit integers
+        let street_number = self.street_number.trim().parse::<i32>();
+        if street_number.as_ref().map(|number| *number <= 0).unwrap_or(true) {
+            errors.entry("street_number".to_string()).or_insert_with(|| "Must be a positive number".to_string());
+        }
+
+        let postal_code = self.postal_code.trim();
+        let postal_code_digits = !postal_code.is_empty() && postal_code.chars().all(|c| c.is_ascii_digit());
+        let postal_code_number = postal_code.parse::<i32>();
+        if !postal_code_digits {
+            errors.entry("postal_code".to_string()).or_insert_with(|| "Must only contain digits".to_string());
+        } else if postal_code_number.is_err() {
+            errors.insert("postal_code".to_string(), "Is too long".to_string());
+        } else if let Some(length) = postal_code_length(&self.country) {
+            if postal_code.len() != length {
+                errors.insert("postal_code".to_string(), format!("Postal codes in {} have {} digits", self.country.trim(), length));
+            }
+        }
+
+        if let Some(website) = optional(&self.website) {
+            if !is_http_url(&website) {
+                errors.insert("website".to_string(), "Must be a http(s) URL".to_string());
+            }
+        }
+        if !self.maps_link.trim().is_empty() && !is_http_url(self.maps_link.trim()) {
+            errors.insert("maps_link".to_string(), "Must be a http(s) URL".to_string());
+        }
+
+        match (street_number, postal_code_number) {
+            (Ok(street_number), Ok(postal_code)) if errors.is_empty() => Ok(LocationMutation {
+                name: self.name.trim().to_string(),
+                website: optional(&self.website),
+                street: self.street.trim().to_string(),
+                street_number: i64::from(street_number),
+                city: self.city.trim().to_string(),
+                postal_code: i64::from(postal_code),
+                country: self.country.trim().to_string(),
+                building: optional(&self.building),
+                maps_link: self.maps_link.trim().to_string(),
+            }),
+            _ => Err(errors),
+        }
+    }
+}
+
+impl From<Location> for LocationForm {
+    fn from(location: Location) -> LocationForm {
+        // postal codes are stored as numbers, which drops leading zeros
+        let postal_code = match postal_code_length(&location.country) {
+            Some(length) => format!("{:0width$}", location.postal_code, width = length),
+            None => location.postal_code.to_string(),
+        };
+
+        LocationForm {
+            name: location.name,
+            website: location.website.unwrap_or_default(),
+            street: location.street,
+            street_number: location.street_number.to_string(),
+            city: location.city,
+            postal_code,
+            country: location.country,
+            building: location.building.unwrap_or_default(),
+            maps_link: location.maps_link,
+        }
+    }
+}
+
 impl LocationMutation {
-    fn add(self) -> io::Result<Location> {
-        let ioerror = |desc| io::Error::new(io::ErrorKind::Other, desc);
-        let body = LocationMutation::build_query(self.into());
-
-
-        let response = match reqwest::blocking::Client::new()
-            .post(&backend_url())
-            .json(&body)
-            .send() {
-            Ok(val) => Ok(val),
-            Err(e) => Err(ioerror(format!("{:#?}", e)))
-        }?;
-        let response: Response<location_mutation::ResponseData> = response.json().map_err(|e|
-            ioerror(format!("Couldn't get successful response from server: {}", e))
-        )?;
-        let data = response.data.ok_or(
-            ioerror(format!("Couldn't get data field from response: {:?}", response.errors.and_then(|x| Some(x.into_iter().map(|x| x.message).collect::<Vec<String>>().join(" | ")))))
-        )?;
+    fn add(self, client: &GraphQLClient) -> error::Result<Location> {
+        let data = client.mutate::<LocationMutation>(self.into())?;
 
         Ok(data
             .location
             .into())
     }
+
+    fn update(self, client: &GraphQLClient, id: i64) -> error::Result<Location> {
+        let data = client.mutate::<UpdateLocationMutation>(update_location_mutation::Variables {
+            id,
+            input: self.into(),
+        })?;
+
+        Ok(data
+            .update_location
+            .into())
+    }
+}
+
+#[derive(GraphQLQuery)]
+#[graphql(
+schema_path = "resources/schema.graphql",
+query_path = "resources/mutations/location.graphql",
+response_derives = "Deserialize, Serialize, Debug"
+)]
+struct UpdateLocationMutation;
+
+impl From<update_location_mutation::UpdateLocationMutationUpdateLocation> for Location {
+    fn from(input: update_location_mutation::UpdateLocationMutationUpdateLocation) -> Location {
+        Location {
+            id: input.id,
+            name: input.name,
+            website: input.website,
+            street: input.street,
+            street_number: input.street_number as i32,
+            city: input.city,
+            postal_code: input.postal_code as i32,
+            country: input.country,
+            building: input.building,
+            maps_link: input.maps_link,
+        }
+    }
+}
+
+impl From<LocationMutation> for update_location_mutation::LocationInput {
+    fn from(input: LocationMutation) -> update_location_mutation::LocationInput {
+        update_location_mutation::LocationInput {
+            name: input.name,
+            website: input.website,
+            street: input.street,
+            street_number: input.street_number,
+            city: input.city,
+            postal_code: input.postal_code,
+            country: input.country,
+            building: input.building,
+            maps_link: input.maps_link,
+        }
+    }
 }
 
-impl Into<Location> for location_mutation::LocationMutationLocation {
-    fn into(self) -> Location {
+impl From<location_mutation::LocationMutationLocation> for Location {
+    fn from(input: location_mutation::LocationMutationLocation) -> Location {
         Location {
-            id: self.id,
-            name: self.name,
-            website: self.website,
-            street: self.street,
-            street_number: self.street_number as i32,
-            city: self.city,
-            postal_code: self.postal_code as i32,
-            country: self.country,
-            building: self.building,
-            maps_link: self.maps_link,
+            id: input.id,
+            name: input.name,
+            website: input.website,
+            street: input.street,
+            street_number: input.street_number as i32,
+            city: input.city,
+            postal_code: input.postal_code as i32,
+            country: input.country,
+            building: input.building,
+            maps_link: input.maps_link,
         }
     }
 }
 
-impl Into<location_mutation::Variables> for LocationMutation {
-    fn into(self) -> location_mutation::Variables {
+impl From<LocationMutation> for location_mutation::Variables {
+    fn from(input: LocationMutation) -> location_mutation::Variables {
         location_mutation::Variables {
             input: location_mutation::LocationInput {
-                name: self.name,
-                website: self.website,
-                street: self.street,
-                street_number: self.street_number,
-                city: self.city,
-                postal_code: self.postal_code,
-                country: self.country,
-                building: self.building,
-                maps_link: self.maps_link,
+                name: input.name,
+                website: input.website,
+                street: input.street,
+                street_number: input.street_number,
+                city: input.city,
+                postal_code: input.postal_code,
+                country: input.country,
+                building: input.building,
+                maps_link: input.maps_link,
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, GraphQLQuery, Serialize)]
+#[graphql(
+schema_path = "resources/schema.graphql",
+query_path = "resources/mutations/event.graphql",
+response_derives = "Deserialize, Serialize, Debug"
+)]
+struct EventMutation {
+    name: String,
+    description: String,
+    time: NaiveDateTime,
+    time_end: NaiveDateTime,
+    price: Option<i64>,
+    currency: Option<String>,
+    location_id: i64,
+    organizer_id: Option<i64>,
+    tags: Vec<i64>,
+}
+
+#[derive(GraphQLQuery)]
+#[graphql(
+schema_path = "resources/schema.graphql",
+query_path = "resources/mutations/event.graphql",
+response_derives = "Deserialize, Serialize, Debug"
+)]
+struct UpdateEventMutation;
+
+/// Years accepted in dates entered by visitors, chrono can't convert times far beyond them between timezones.
+const INPUT_YEARS: RangeInclusive<i32> = 1..=9999;
+
+/// `datetime-local` inputs send minutes, some browsers also append seconds.
+fn parse_datetime_input(value: &str) -> Result<NaiveDateTime, chrono::ParseError> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S"))
+}
+
+/// Raw event form input, kept as strings so that invalid values can be shown again.
+/// The times are local times in the timezone of the visitor, as sent by the `datetime-local` inputs.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+struct EventForm {
+    name: String,
+    description: String,
+    time: String,
+    time_end: String,
+    price: String,
+    currency: String,
+    location_id: String,
+    organizer_id: String,
+    tags: Vec<String>,
+}
+
+// the derived `FromForm` can't collect the repeated `tags` values of a multi-select
+impl<'f> FromForm<'f> for EventForm {
+    type Error = std::convert::Infallible;
+
+    fn from_form(items: &mut FormItems<'f>, _strict: bool) -> Result<EventForm, Self::Error> {
+        let mut form = EventForm::default();
+
+        for item in items {
+            let value = item.value.url_decode_lossy();
+            match item.key.as_str() {
+                "name" => form.name = value,
+                "description" => form.description = value,
+                "time" => form.time = value,
+                "time_end" => form.time_end = value,
+                "price" => form.price = value,
+                "currency" => form.currency = value,
+                "location_id" => form.location_id = value,
+                "organizer_id" => form.organizer_id = value,
+                "tags" => form.tags.push(value),
+                _ => {}
+            }
+        }
+
+        Ok(form)
+    }
+}
+
+impl EventForm {
+    fn from_event(event: Event, timezone: Tz) -> EventForm {
+        let local = |time: &NaiveDateTime| timezone.from_utc_datetime(time).format("%Y-%m-%dT%H:%M").to_string();
+
+        EventForm {
+            time: local(&event.time),
+            time_end: local(&event.time_end),
+            name: event.name,
+            description: event.description,
+            price: event.price.map(|price| price.to_string()).unwrap_or_default(),
+            currency: event.currency.unwrap_or_default(),
+            location_id: event.location.id.to_string(),
+            organizer_id: event.organizer.map(|organizer| organizer.id.to_string()).unwrap_or_default(),
+            tags: event.tags.iter().map(|tag| tag.id.to_string()).collect(),
+        }
+    }
+
+    /// The times of the returned mutation are still local times, see `EventMutation::with_local_times`.
+    fn validate(&self) -> Result<EventMutation, FieldErrors> {
+        let mut errors = FieldErrors::new();
+        let optional = |value: &str| Some(value.trim().to_string()).filter(|value| !value.is_empty());
+
+        for (field, value) in [
+            ("name", &self.name),
+            ("time", &self.time),
+            ("time_end", &self.time_end),
+            ("location_id", &self.location_id),
+        ].iter() {
+            if value.trim().is_empty() {
+                errors.insert(field.to_string(), "This field is required".to_string());
+            }
+        }
+
+        let mut datetime = |field: &str, value: &str| match parse_datetime_input(value.trim()) {
+            Ok(time) if INPUT_YEARS.contains(&time.year()) => Some(time),
+            Ok(_) => {
+                errors.entry(field.to_string()).or_insert_with(|| format!("Must be between the years {} and {}", INPUT_YEARS.start(), INPUT_YEARS.end()));
+                None
+            }
+            Err(_) => {
+                errors.entry(field.to_string()).or_insert_with(|| "Must be a date and time".to_string());
+                None
+            }
+        };
+        let time = datetime("time", &self.time);
+        let time_end = datetime("time_end", &self.time_end);
+        if let (Some(time), Some(time_end)) = (time, time_end) {
+            if time_end < time {
+                errors.insert("time_end".to_string(), "The event can't end before it starts".to_string());
+            }
+        }
+
+        let price = optional(&self.price).map(|price| price.parse::<i64>()).transpose();
+        match price {
+            Ok(Some(price)) if price < 0 => {
+                errors.insert("price".to_string(), "Must not be negative".to_string());
+            }
+            Err(_) => {
+                errors.insert("price".to_string(), "Must be a whole number".to_string());
+            }
+            _ => {}
+        }
+
+        let location_id = self.location_id.trim().parse::<i64>();
+        if location_id.is_err() {
+            errors.entry("location_id".to_string()).or_insert_with(|| "Unknown location".to_string());
+        }
+        let organizer_id = optional(&self.organizer_id).map(|id| id.parse::<i64>()).transpose();
+        if organizer_id.is_err() {
+            errors.insert("organizer_id".to_string(), "Unknown organizer".to_string());
+        }
+        let tags = self.tags.iter().map(|tag| tag.trim().parse::<i64>()).collect::<Result<Vec<_>, _>>();
+        if tags.is_err() {
+            errors.insert("tags".to_string(), "Unknown tag".to_string());
+        }
+
+        match (time, time_end, price, location_id, organizer_id, tags) {
+            (Some(time), Some(time_end), Ok(price), Ok(location_id), Ok(organizer_id), Ok(tags)) if errors.is_empty() => Ok(EventMutation {
+                name: self.name.trim().to_string(),
+                description: self.description.trim().to_string(),
+                time,
+                time_end,
+                price,
+                currency: optional(&self.currency),
+                location_id,
+                organizer_id,
+                tags,
+            }),
+            _ => Err(errors),
+        }
+    }
+}
+
+impl EventMutation {
+    /// The edit form shows the times in the timezone of the visitor, the backend stores them in UTC.
+    ///
+    /// Fails with errors for the form fields of times which can't be converted.
+    fn with_local_times(self, timezone: Tz) -> Result<EventMutation, FieldErrors> {
+        match (calendar::local_to_utc(timezone, self.time), calendar::local_to_utc(timezone, self.time_end)) {
+            (Some(time), Some(time_end)) => Ok(EventMutation {
+                time,
+                time_end,
+                ..self
+            }),
+            (time, time_end) => {
+                let mut errors = FieldErrors::new();
+                for (field, time) in [("time", time), ("time_end", time_end)].iter() {
+                    if time.is_none() {
+                        errors.insert(field.to_string(), format!("Can't be converted from {} to UTC", timezone.name()));
+                    }
+                }
+                Err(errors)
+            }
+        }
+    }
+
+    fn add(self, client: &GraphQLClient) -> error::Result<i64> {
+        let data = client.mutate::<EventMutation>(self.into())?;
+
+        Ok(data.event.id)
+    }
+
+    fn update(self, client: &GraphQLClient, id: i64) -> error::Result<i64> {
+        let data = client.mutate::<UpdateEventMutation>(update_event_mutation::Variables {
+            id,
+            input: self.into(),
+        })?;
+
+        Ok(data.update_event.id)
+    }
+}
+
+#[derive(GraphQLQuery)]
+#[graphql(
+schema_path = "resources/schema.graphql",
+query_path = "resources/mutations/delete.graphql",
+response_derives = "Deserialize, Serialize, Debug"
+)]
+struct DeleteEventMutation;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+schema_path = "resources/schema.graphql",
+query_path = "resources/mutations/delete.graphql",
+response_derives = "Deserialize, Serialize, Debug"
+)]
+struct DeleteLocationMutation;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+schema_path = "resources/schema.graphql",
+query_path = "resources/mutations/delete.graphql",
+response_derives = "Deserialize, Serialize, Debug"
+)]
+struct DeleteOrganizerMutation;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+schema_path = "resources/schema.graphql",
+query_path = "resources/mutations/delete.graphql",
+response_derives = "Deserialize, Serialize, Debug"
+)]
+struct DeleteTagMutation;
+
+fn delete_event(client: &GraphQLClient, id: i64) -> error::Result<()> {
+    client.mutate::<DeleteEventMutation>(delete_event_mutation::Variables { id })?;
+
+    Ok(())
+}
+
+fn delete_location(client: &GraphQLClient, id: i64) -> error::Result<()> {
+    client.mutate::<DeleteLocationMutation>(delete_location_mutation::Variables { id })?;
+
+    Ok(())
+}
+
+fn delete_organizer(client: &GraphQLClient, id: i64) -> error::Result<()> {
+    client.mutate::<DeleteOrganizerMutation>(delete_organizer_mutation::Variables { id })?;
+
+    Ok(())
+}
+
+fn delete_tag(client: &GraphQLClient, id: i64) -> error::Result<()> {
+    client.mutate::<DeleteTagMutation>(delete_tag_mutation::Variables { id })?;
+
+    Ok(())
+}
+
+impl From<Event> for EventMutation {
+    fn from(event: Event) -> EventMutation {
+        EventMutation {
+            name: event.name,
+            description: event.description,
+            time: event.time,
+            time_end: event.time_end,
+            price: event.price,
+            currency: event.currency,
+            location_id: event.location.id,
+            organizer_id: event.organizer.map(|organizer| organizer.id),
+            tags: event.tags.into_iter().map(|tag| tag.id).collect(),
+        }
+    }
+}
+
+impl From<EventMutation> for event_mutation::Variables {
+    fn from(input: EventMutation) -> event_mutation::Variables {
+        event_mutation::Variables {
+            input: event_mutation::EventInput {
+                name: input.name,
+                description: input.description,
+                timestamp: input.time.timestamp().to_string(),
+                timestamp_end: input.time_end.timestamp().to_string(),
+                price: input.price,
+                currency: input.currency,
+                location_id: input.location_id,
+                organizer_id: input.organizer_id,
+                tags: input.tags,
             }
         }
     }
 }
 
+impl From<EventMutation> for update_event_mutation::EventInput {
+    fn from(input: EventMutation) -> update_event_mutation::EventInput {
+        update_event_mutation::EventInput {
+            name: input.name,
+            description: input.description,
+            timestamp: input.time.timestamp().to_string(),
+            timestamp_end: input.time_end.timestamp().to_string(),
+            price: input.price,
+            currency: input.currency,
+            location_id: input.location_id,
+            organizer_id: input.organizer_id,
+            tags: input.tags,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, GraphQLQuery, Serialize)]
 #[graphql(
 schema_path = "resources/schema.graphql",
@@ -147,20 +624,93 @@ struct Tag {
 }
 
 impl Location {
+    fn address(&self) -> String {
+        format!("{} {}, {} {}", self.street, self.street_number, self.postal_code, self.city)
+    }
+
     fn to_ics(&self) -> ics::properties::Location {
-        ics::properties::Location::new(
-            format!("{} {}, {} {}", self.street, self.street_number, self.postal_code, self.city)
-        )
+        ics::properties::Location::new(calendar::text(&self.address()))
+    }
+
+    /// schema.org `Place` of the location.
+    fn json_ld(&self, site: &Site) -> serde_json::Value {
+        let mut place = serde_json::json!({
+            "@type": "Place",
+            "name": self.name,
+            "url": site.url(&format!("/location/{}", self.id)),
+            "hasMap": self.maps_link,
+            "address": {
+                "@type": "PostalAddress",
+                "streetAddress": format!("{} {}", self.street, self.street_number),
+                "postalCode": self.postal_code.to_string(),
+                "addressLocality": self.city,
+                "addressCountry": self.country,
+            },
+        });
+        if let Some((latitude, longitude)) = self.coordinates() {
+            place["geo"] = serde_json::json!({
+                "@type": "GeoCoordinates",
+                "latitude": latitude,
+                "longitude": longitude,
+            });
+        }
+
+        place
+    }
+
+    /// Reads latitude and longitude from map links like `https://www.google.com/maps/@52.52,13.40,15z`,
+    /// `https://maps.google.com/?q=52.52,13.40` or `https://www.openstreetmap.org/?mlat=52.52&mlon=13.40`.
+    fn coordinates(&self) -> Option<(f64, f64)> {
+        let link = self.maps_link.replace("%2C", ",").replace("%2c", ",");
+        let number = |value: &str| value
+            .split(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+            .next()
+            .and_then(|value| value.parse::<f64>().ok());
+        let valid = |(latitude, longitude): (f64, f64)| latitude.abs() <= 90.0 && longitude.abs() <= 180.0;
+
+        let parameter = |name: &str| link
+            .split(|c| c == '?' || c == '&')
+            .find_map(|pair| pair.strip_prefix(name).and_then(|value| value.strip_prefix('=')))
+            .and_then(number);
+        if let (Some(latitude), Some(longitude)) = (parameter("mlat"), parameter("mlon")) {
+            return Some((latitude, longitude)).filter(|&coordinates| valid(coordinates));
+        }
+
+        link
+            .split(|c| c == '@' || c == '=')
+            .skip(1)
+            .find_map(|part| {
+                let mut values = part.splitn(2, ',');
+                let latitude = values.next().and_then(number)?;
+                let longitude = values.next().and_then(number)?;
+
+                Some((latitude, longitude)).filter(|&coordinates| valid(coordinates))
+            })
     }
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, GraphQLQuery, Serialize)]
+#[graphql(
+schema_path = "resources/schema.graphql",
+query_path = "resources/organizer.graphql",
+response_derives = "Deserialize, Serialize, Debug"
+)]
 struct Organizer {
     id: i64,
     name: String,
     website: Option<String>,
 }
 
+impl From<organizer::OrganizerOrganizer> for Organizer {
+    fn from(input: organizer::OrganizerOrganizer) -> Organizer {
+        Organizer {
+            id: input.id,
+            name: input.name,
+            website: input.website,
+        }
+    }
+}
+
 impl From<location::LocationLocation> for Location {
     fn from(input: location::LocationLocation) -> Location {
         Location {
@@ -178,18 +728,30 @@ impl From<location::LocationLocation> for Location {
     }
 }
 
-impl From<tag::TagTag> for Tag {
-    fn from(tag: tag::TagTag) -> Self {
-        Tag {
+/// The backend sends timestamps as strings of unix seconds.
+fn parse_timestamp(value: &str) -> error::Result<NaiveDateTime> {
+    let timestamp = value
+        .parse::<i64>()
+        .map_err(|e| Error::Decode(format!("invalid timestamp {:?}: {}", value, e)))?;
+
+    NaiveDateTime::from_timestamp_opt(timestamp, 0)
+        .ok_or_else(|| Error::Decode(format!("timestamp {:?} out of range", value)))
+}
+
+impl TryFrom<tag::TagTag> for Tag {
+    type Error = Error;
+
+    fn try_from(tag: tag::TagTag) -> error::Result<Self> {
+        Ok(Tag {
             id: tag.id,
             name: tag.name,
             description: tag.description,
-            events: tag.events.into_iter().map(|event: tag::TagTagEvents| Event {
+            events: tag.events.into_iter().map(|event: tag::TagTagEvents| Ok(Event {
                 id: event.id,
                 name: event.name,
                 description: event.description,
-                time: NaiveDateTime::from_timestamp(event.timestamp.parse::<i64>().unwrap(), 0),
-                time_end: NaiveDateTime::from_timestamp(event.timestamp_end.parse::<i64>().unwrap(), 0),
+                time: parse_timestamp(&event.timestamp)?,
+                time_end: parse_timestamp(&event.timestamp_end)?,
                 price: event.price,
                 currency: event.currency,
                 location: Location {
@@ -204,11 +766,11 @@ impl From<tag::TagTag> for Tag {
                     building: event.location.building,
                     maps_link: event.location.maps_link,
                 },
-                organizer: event.organizer.and_then(|organizer| Some(Organizer {
+                organizer: event.organizer.map(|organizer| Organizer {
                     id: organizer.id,
                     name: organizer.name,
                     website: organizer.website,
-                })),
+                }),
                 tags: event
                     .tags
                     .into_iter()
@@ -218,19 +780,21 @@ impl From<tag::TagTag> for Tag {
                         description: tag.description,
                     })
                     .collect(),
-            }).collect::<Vec<Event>>(),
-        }
+            })).collect::<error::Result<Vec<Event>>>()?,
+        })
     }
 }
 
-impl From<event::EventEvent> for Event {
-    fn from(event: event::EventEvent) -> Event {
-        Event {
+impl TryFrom<event::EventEvent> for Event {
+    type Error = Error;
+
+    fn try_from(event: event::EventEvent) -> error::Result<Event> {
+        Ok(Event {
             id: event.id,
             name: event.name,
             description: event.description,
-            time: NaiveDateTime::from_timestamp(event.timestamp.parse::<i64>().unwrap(), 0),
-            time_end: NaiveDateTime::from_timestamp(event.timestamp_end.parse::<i64>().unwrap(), 0),
+            time: parse_timestamp(&event.timestamp)?,
+            time_end: parse_timestamp(&event.timestamp_end)?,
             price: event.price,
             currency: event.currency,
             location: Location {
@@ -245,37 +809,39 @@ impl From<event::EventEvent> for Event {
                 building: event.location.building,
                 maps_link: event.location.maps_link,
             },
-            organizer: event.organizer.and_then(|organizer| Some(Organizer {
+            organizer: event.organizer.map(|organizer| Organizer {
                 id: organizer.id,
                 name: organizer.name,
                 website: organizer.website,
-            })),
+            }),
             tags: event
                 .tags
                 .into_iter()
                 .map(|tag| tag.into())
                 .collect(),
-        }
+        })
     }
 }
 
-impl Into<InnerEventTag> for event::EventEventTags {
-    fn into(self) -> InnerEventTag {
+impl From<event::EventEventTags> for InnerEventTag {
+    fn from(tag: event::EventEventTags) -> InnerEventTag {
         InnerEventTag {
-            id: self.id,
-            name: self.name,
-            description: self.description,
+            id: tag.id,
+            name: tag.name,
+            description: tag.description,
         }
     }
 }
 
 
+#[derive(Default)]
 struct TagInput {
     id: Option<i64>,
     name: Option<String>,
     description: Option<String>,
 }
 
+#[derive(Default)]
 struct LocationInput {
     id: Option<i64>,
     name: Option<String>,
@@ -289,11 +855,22 @@ struct LocationInput {
     maps_link: Option<String>,
 }
 
+#[derive(Default)]
+struct OrganizerInput {
+    id: Option<i64>,
+    name: Option<String>,
+    website: Option<String>,
+}
+
+/// Values of the events to query, all other fields are matched exactly.
+#[derive(Clone, Default)]
 struct EventInput {
     id: Option<i64>,
     name: Option<String>,
     description: Option<String>,
+    /// earliest start of the events
     time: Option<NaiveDateTime>,
+    /// start of the events has to be before this time
     time_end: Option<NaiveDateTime>,
     price: Option<i64>,
     currency: Option<String>,
@@ -301,8 +878,8 @@ struct EventInput {
     organizer_id: Option<i64>,
 }
 
-impl Into<event::Variables> for EventInput {
-    fn into(self) -> event::Variables {
+impl From<EventInput> for event::Variables {
+    fn from(input: EventInput) -> event::Variables {
         let default_pagination = PaginationContext::default();
         event::Variables {
             constraints: Some(event::Constraints {
@@ -310,22 +887,22 @@ impl Into<event::Variables> for EventInput {
                 offset: default_pagination.offset.to_string(),
             }),
             input: Some(event::EventQuery {
-                id: self.id,
-                name: self.name,
-                description: self.description,
-                timestamp: self.time.and_then(|t| Some(t.timestamp().to_string())),
-                timestamp_end: self.time_end.and_then(|t| Some(t.timestamp().to_string())),
-                price: self.price,
-                currency: self.currency,
-                location_id: self.location_id,
-                organizer_id: self.organizer_id,
+                id: input.id,
+                name: input.name,
+                description: input.description,
+                timestamp: input.time.map(|t| t.timestamp().to_string()),
+                timestamp_end: input.time_end.map(|t| t.timestamp().to_string()),
+                price: input.price,
+                currency: input.currency,
+                location_id: input.location_id,
+                organizer_id: input.organizer_id,
             }),
         }
     }
 }
 
-impl Into<tag::Variables> for TagInput {
-    fn into(self) -> tag::Variables {
+impl From<TagInput> for tag::Variables {
+    fn from(input: TagInput) -> tag::Variables {
         let default_pagination = PaginationContext::default();
 
         tag::Variables {
@@ -335,34 +912,52 @@ impl Into<tag::Variables> for TagInput {
             }),
             input: Some(tag::TagQuery
             {
-                id: self.id,
-                name: self.name,
-                description: self.description,
+                id: input.id,
+                name: input.name,
+                description: input.description,
             }),
         }
     }
 }
 
-impl Into<location::Variables> for LocationInput {
-    fn into(self) -> location::Variables {
+impl From<OrganizerInput> for organizer::Variables {
+    fn from(input: OrganizerInput) -> organizer::Variables {
         let default_pagination = PaginationContext::default();
 
-        location::Variables {
-            constraints: Some(location::Constraints {
+        organizer::Variables {
+            constraints: Some(organizer::Constraints {
                 limit: default_pagination.limit.to_string(),
                 offset: default_pagination.offset.to_string(),
             }),
-            input: Some(location::LocationQuery {
-                id: self.id,
-                name: None,
-                website: None,
-                street: None,
-                street_number: None,
-                city: None,
-                postal_code: None,
-                country: None,
-                building: None,
-                maps_link: None,
+            input: Some(organizer::OrganizerQuery {
+                id: input.id,
+                name: input.name,
+                website: input.website,
+            }),
+        }
+    }
+}
+
+impl From<LocationInput> for location::Variables {
+    fn from(input: LocationInput) -> location::Variables {
+        let default_pagination = PaginationContext::default();
+
+        location::Variables {
+            constraints: Some(location::Constraints {
+                limit: default_pagination.limit.to_string(),
+                offset: default_pagination.offset.to_string(),
+            }),
+            input: Some(location::LocationQuery {
+                id: input.id,
+                name: input.name,
+                website: input.website,
+                street: input.street,
+                street_number: input.street_number.map(i64::from),
+                city: input.city,
+                postal_code: input.postal_code.map(i64::from),
+                country: input.country,
+                building: input.building,
+                maps_link: input.maps_link,
             }),
         }
     }
@@ -395,22 +990,23 @@ struct Event {
 }
 
 // TODO: return an event without a location
-fn get_events_for_location(location_id: i64) -> io::Result<Vec<Event>> {
-    get_events(EventInput {
-        id: None,
-        name: None,
-        description: None,
-        time: None,
-        time_end: None,
-        price: None,
-        currency: None,
+fn get_events_for_location(client: &GraphQLClient, location_id: i64) -> error::Result<Vec<Event>> {
+    get_all_events_matching(client, Some(EventInput {
         location_id: Some(location_id),
-        organizer_id: None,
-    }.into())
+        ..EventInput::default()
+    }))
 }
 
-fn get_location(id: i64) -> io::Result<Location> {
-    get_locations(LocationInput {
+// TODO: return an event without an organizer
+fn get_events_for_organizer(client: &GraphQLClient, organizer_id: i64) -> error::Result<Vec<Event>> {
+    get_all_events_matching(client, Some(EventInput {
+        organizer_id: Some(organizer_id),
+        ..EventInput::default()
+    }))
+}
+
+fn get_location(client: &GraphQLClient, id: i64) -> error::Result<Location> {
+    get_locations(client, LocationInput {
         id: Some(id),
         name: None,
         website: None,
@@ -423,58 +1019,166 @@ fn get_location(id: i64) -> io::Result<Location> {
         maps_link: None,
     }.into())?
         .pop()
-        .ok_or(io::Error::from(io::ErrorKind::NotFound))
+        .ok_or(Error::NotFound)
 }
 
+fn get_organizer(client: &GraphQLClient, id: i64) -> error::Result<Organizer> {
+    get_organizers(client, OrganizerInput {
+        id: Some(id),
+        name: None,
+        website: None,
+    }.into())?
+        .pop()
+        .ok_or(Error::NotFound)
+}
 
-fn get_tag(id: i64) -> io::Result<Tag> {
-    get_tags(TagInput {
+fn get_tag(client: &GraphQLClient, id: i64) -> error::Result<Tag> {
+    get_tags(client, TagInput {
         id: Some(id),
         name: None,
         description: None,
     }.into())?
         .pop()
-        .ok_or(io::Error::from(io::ErrorKind::NotFound))
+        .ok_or(Error::NotFound)
+}
+
+fn get_all_tags(client: &GraphQLClient) -> error::Result<Vec<Tag>> {
+    let mut pagination = PaginationContext::default();
+    let mut tags = Vec::new();
+
+    loop {
+        let mut input: tag::Variables = TagInput {
+            id: None,
+            name: None,
+            description: None,
+        }.into();
+        input.constraints = Some(tag::Constraints {
+            limit: pagination.limit.to_string(),
+            offset: pagination.offset.to_string(),
+        });
+        let page = get_tags(client, input)?;
+        let last_page = page.len() < pagination.limit as usize;
+        tags.extend(page);
+
+        if last_page {
+            return Ok(tags);
+        }
+        pagination.offset += pagination.limit;
+    }
 }
 
-fn get_tags(variables: tag::Variables) -> io::Result<Vec<Tag>> {
-    let ioerror = |desc| io::Error::new(io::ErrorKind::Other, desc);
-    let body = Tag::build_query(variables);
+fn get_all_locations(client: &GraphQLClient) -> error::Result<Vec<Location>> {
+    let mut pagination = PaginationContext::default();
+    let mut locations = Vec::new();
 
-    let client = reqwest::blocking::Client::new();
-    let res = match client.post(&backend_url()).json(&body).send() {
-        Ok(val) => Ok(val),
-        Err(e) => Err(ioerror(format!("{:#?}", e)))
-    }?;
-    let response: Response<tag::ResponseData> = res.json().map_err(|e|
-        ioerror(format!("Couldn't get successful response from server: {}", e))
-    )?;
-    let data = response.data.ok_or(
-        ioerror(format!("Couldn't get data field from response: {:?}", response.errors.and_then(|x| Some(x.into_iter().map(|x| x.message).collect::<Vec<String>>().join(" | ")))))
-    )?;
+    loop {
+        let mut input: location::Variables = LocationInput::default().into();
+        input.constraints = Some(location::Constraints {
+            limit: pagination.limit.to_string(),
+            offset: pagination.offset.to_string(),
+        });
+        let page = get_locations(client, input)?;
+        let last_page = page.len() < pagination.limit as usize;
+        locations.extend(page);
 
-    Ok(data
+        if last_page {
+            return Ok(locations);
+        }
+        pagination.offset += pagination.limit;
+    }
+}
+
+fn get_all_organizers(client: &GraphQLClient) -> error::Result<Vec<Organizer>> {
+    let mut pagination = PaginationContext::default();
+    let mut organizers = Vec::new();
+
+    loop {
+        let mut input: organizer::Variables = OrganizerInput::default().into();
+        input.constraints = Some(organizer::Constraints {
+            limit: pagination.limit.to_string(),
+            offset: pagination.offset.to_string(),
+        });
+        let page = get_organizers(client, input)?;
+        let last_page = page.len() < pagination.limit as usize;
+        organizers.extend(page);
+
+        if last_page {
+            return Ok(organizers);
+        }
+        pagination.offset += pagination.limit;
+    }
+}
+
+/// All events, only for what a query can't narrow down, like searching in the names and descriptions.
+///
+/// The backend returns at most a page per query and in no particular order, its fields match exact values only. Lists
+/// are fetched page by page and, if they're sorted, combined or filtered beyond what a query selects, paged here with
+/// `page_of`. Events limited to a time range are queried with `get_events_starting` instead.
+fn get_all_events(client: &GraphQLClient) -> error::Result<Vec<Event>> {
+    get_all_events_matching(client, None)
+}
+
+/// All events matching `input`.
+fn get_all_events_matching(client: &GraphQLClient, input: Option<EventInput>) -> error::Result<Vec<Event>> {
+    let mut pagination = PaginationContext::default();
+    let mut events = Vec::new();
+
+    loop {
+        let page = get_events(client, event::Variables {
+            constraints: Some(event::Constraints {
+                offset: pagination.offset.to_string(),
+                limit: pagination.limit.to_string(),
+            }),
+            input: input.clone().and_then(|input| event::Variables::from(input).input),
+        })?;
+        let last_page = page.len() < pagination.limit as usize;
+        events.extend(page);
+
+        if last_page {
+            return Ok(events);
+        }
+        pagination.offset += pagination.limit;
+    }
+}
+
+/// Events matching `input` which start at or after `from` and before `to`, sorted by start time, `None` bounds are open.
+fn get_events_starting(client: &GraphQLClient, from: Option<NaiveDateTime>, to: Option<NaiveDateTime>, input: EventInput) -> error::Result<Vec<Event>> {
+    let mut events = get_all_events_matching(client, Some(EventInput {
+        time: from,
+        time_end: to,
+        ..input
+    }))?;
+    events.retain(|event| from.map_or(true, |from| event.time >= from) && to.map_or(true, |to| event.time < to));
+    events.sort_by_key(|event| event.time);
+
+    Ok(events)
+}
+
+/// How long before a time range events are looked up which could still be running in it.
+///
+/// The backend selects events by their start, events running for longer are missing from lists of current events.
+const RUNNING_EVENT_DAYS: i64 = 7;
+
+/// Events matching `input` which haven't ended at `from` and start before `to`, sorted by start time.
+fn get_events_running(client: &GraphQLClient, from: NaiveDateTime, to: Option<NaiveDateTime>, input: EventInput) -> error::Result<Vec<Event>> {
+    let mut events = get_events_starting(client, from.checked_sub_signed(Duration::days(RUNNING_EVENT_DAYS)), to, input)?;
+    events.retain(|event| event.time_end >= from);
+
+    Ok(events)
+}
+
+fn get_tags(client: &GraphQLClient, variables: tag::Variables) -> error::Result<Vec<Tag>> {
+    let data = client.query::<Tag>(variables)?;
+
+    data
         .tag
         .into_iter()
-        .map(From::from)
-        .collect::<Vec<Tag>>())
+        .map(Tag::try_from)
+        .collect::<error::Result<Vec<Tag>>>()
 }
 
-fn get_locations(variables: location::Variables) -> io::Result<Vec<Location>> {
-    let ioerror = |desc| io::Error::new(io::ErrorKind::Other, desc);
-    let body = Location::build_query(variables);
-
-    let client = reqwest::blocking::Client::new();
-    let res = match client.post(&backend_url()).json(&body).send() {
-        Ok(val) => Ok(val),
-        Err(e) => Err(ioerror(format!("{:#?}", e)))
-    }?;
-    let response: Response<location::ResponseData> = res.json().map_err(|e|
-        ioerror(format!("Couldn't get successful response from server: {}", e))
-    )?;
-    let data = response.data.ok_or(
-        ioerror(format!("Couldn't get data field from response: {:?}", response.errors.and_then(|x| Some(x.into_iter().map(|x| x.message).collect::<Vec<String>>().join(" | ")))))
-    )?;
+fn get_locations(client: &GraphQLClient, variables: location::Variables) -> error::Result<Vec<Location>> {
+    let data = client.query::<Location>(variables)?;
 
     Ok(data
         .location
@@ -483,48 +1187,181 @@ fn get_locations(variables: location::Variables) -> io::Result<Vec<Location>> {
         .collect::<Vec<Location>>())
 }
 
-fn get_events(variables: event::Variables) -> io::Result<Vec<Event>> {
-    let ioerror = |desc| io::Error::new(io::ErrorKind::Other, desc);
-    let body = Event::build_query(variables);
-
-    let client = reqwest::blocking::Client::new();
-    let res = match client.post(&backend_url()).json(&body).send() {
-        Ok(val) => Ok(val),
-        Err(e) => Err(ioerror(format!("{:#?}", e)))
-    }?;
-    let response: Response<event::ResponseData> = res.json().map_err(|e|
-        ioerror(format!("Couldn't get successful response from server: {}", e))
-    )?;
-    let data = response.data.ok_or(
-        ioerror(format!("Couldn't get data field from response: {:?}", response.errors.and_then(|x| Some(x.into_iter().map(|x| x.message).collect::<Vec<String>>().join(" | ")))))
-    )?;
+fn get_organizers(client: &GraphQLClient, variables: organizer::Variables) -> error::Result<Vec<Organizer>> {
+    let data = client.query::<Organizer>(variables)?;
 
     Ok(data
-        .event
+        .organizer
         .into_iter()
         .map(From::from)
-        .collect::<Vec<Event>>())
+        .collect::<Vec<Organizer>>())
+}
+
+fn get_events(client: &GraphQLClient, variables: event::Variables) -> error::Result<Vec<Event>> {
+    let data = client.query::<Event>(variables)?;
+
+    data
+        .event
+        .into_iter()
+        .map(Event::try_from)
+        .collect::<error::Result<Vec<Event>>>()
 }
 
 impl Event {
-    fn get_ics(&self) -> ics::ICalendar {
-        let mut calendar = ics::ICalendar::new("2.0", "ics-rs");
-        let calendar_uuid = uuid::Uuid::new_v4().to_string();
-        let dtstamp = self.time.format("%Y%m%dT%H%M%S").to_string();
-        let dtendstamp = self.time_end.format("%Y%m%dT%H%M%S").to_string();
-        let mut event = ics::Event::new(calendar_uuid, dtstamp.clone());
-        event.push(Created::new(dtstamp.clone()));
-        event.push(DtStart::new(dtstamp.clone()));
-        event.push(DtEnd::new(dtendstamp));
-        event.push(Summary::new(&self.name));
-        event.push(Description::new(&self.description));
+    fn get_ics(&self, site: &Site, timezone: Tz) -> ics::ICalendar {
+        ics_calendar(site, timezone, None, std::slice::from_ref(self))
+    }
+
+    fn to_ics(&self, site: &Site, timezone: Tz) -> ics::Event {
+        let local_time = |time: &NaiveDateTime| timezone.from_utc_datetime(time).format("%Y%m%dT%H%M%S").to_string();
+        let dtstamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        // has to stay the same across downloads, calendar clients update the event instead of adding it again
+        let mut event = ics::Event::new(format!("event-{}@{}", self.id, site.uid_domain), dtstamp);
+
+        let mut start = DtStart::new(local_time(&self.time));
+        start.add(TzIDParam::new(timezone.name()));
+        event.push(start);
+        let mut end = DtEnd::new(local_time(&self.time_end));
+        end.add(TzIDParam::new(timezone.name()));
+        event.push(end);
+
+        event.push(Summary::new(calendar::text(&self.name)));
+        event.push(Description::new(calendar::text(&self.description)));
         event.push(self.location.to_ics());
-        event.push(Status::needs_action());
+        if let Some((latitude, longitude)) = self.location.coordinates() {
+            event.push(Geo::new(format!("{:.6};{:.6}", latitude, longitude)));
+        }
+        if let Some(organizer) = &self.organizer {
+            let address = organizer
+                .website
+                .clone()
+                .unwrap_or_else(|| site.url(&format!("/organizer/{}", organizer.id)));
+            let mut property = ics::properties::Organizer::new(address);
+            // parameter values can't contain quotes, the quotes allow `:`, `;` and `,`
+            property.add(CN::new(format!("\"{}\"", organizer.name.replace('"', "'"))));
+            event.push(property);
+        }
+        if !self.tags.is_empty() {
+            event.push(Categories::new(self.tags
+                .iter()
+                .map(|tag| calendar::text(&tag.name))
+                .collect::<Vec<String>>()
+                .join(",")));
+        }
+        event.push(Status::confirmed());
         event.push(Class::public());
-        event.push(URL::new(format!("https://192.168.178.51:8000/event/{}", self.id))); // TODO: update base url
+        event.push(URL::new(site.url(&format!("/event/{}", self.id))));
+
+        event
+    }
+}
+
+/// A calendar containing `events` and the VTIMEZONE their times refer to.
+fn ics_calendar<'a>(site: &Site, timezone: Tz, name: Option<String>, events: &'a [Event]) -> ics::ICalendar<'a> {
+    let mut calendar = ics::ICalendar::new("2.0", "-//events_frontend//NONSGML Events//EN");
+    calendar.push(Method::new("PUBLISH"));
+    if let Some(name) = name {
+        calendar.push(ics::components::Property::new("X-WR-CALNAME", calendar::text(&name)));
+    }
+
+    let local_year = |time: &NaiveDateTime| timezone.from_utc_datetime(time).year();
+    let years = events
+        .iter()
+        .flat_map(|event| vec![local_year(&event.time), local_year(&event.time_end)]);
+    let this_year = Utc::now().year();
+    let (earliest, latest) = (this_year - calendar::TIMEZONE_YEARS, this_year + calendar::TIMEZONE_YEARS);
+    let from_year = years.clone().min().unwrap_or(this_year).clamp(earliest, latest);
+    let to_year = years.max().unwrap_or(this_year).clamp(from_year, latest);
+    calendar.add_timezone(calendar::timezone(timezone, from_year, to_year));
+
+    events
+        .iter()
+        .for_each(|event| calendar.add_event(event.to_ics(site, timezone)));
+
+    calendar
+}
+
+fn ics_response(calendar: ics::ICalendar) -> error::Result<Content<Stream<Cursor<Vec<u8>>>>> {
+    let mut buffer = Vec::new();
+    calendar.write(&mut buffer)?;
 
-        calendar.add_event(event);
-        calendar
+    Ok(Content(http::ContentType::Calendar, Stream::from(Cursor::new(buffer))))
+}
+
+/// Calendar feeds only contain events which end at most `past_days` ago and start at most `future_days` from now.
+#[derive(Clone, Copy, Debug)]
+struct FeedWindow {
+    past_days: i64,
+    future_days: i64,
+    /// upper bound of both, larger values are clamped
+    max_days: i64,
+}
+
+impl FeedWindow {
+    /// Uses the `feed_past_days`/`feed_future_days`/`feed_max_days` settings of the rocket config.
+    fn from_config(config: &rocket::Config) -> FeedWindow {
+        let max_days = config.get_int("feed_max_days").unwrap_or(3650).max(0);
+        let days = |name, default| config.get_int(name).unwrap_or(default).max(0).min(max_days);
+
+        FeedWindow {
+            past_days: days("feed_past_days", 30),
+            future_days: days("feed_future_days", 365),
+            max_days,
+        }
+    }
+
+    /// Subscribers can narrow or widen the window with `?past_days=` and `?future_days=`, up to `max_days`.
+    fn with(self, past_days: Option<u32>, future_days: Option<u32>) -> FeedWindow {
+        let days = |days: Option<u32>, default| days.map_or(default, |days| i64::from(days).min(self.max_days));
+
+        FeedWindow {
+            past_days: days(past_days, self.past_days),
+            future_days: days(future_days, self.future_days),
+            ..self
+        }
+    }
+
+    /// The events of the window which haven't ended yet, for feeds.
+    fn upcoming(self) -> FeedWindow {
+        self.with(Some(0), None)
+    }
+
+    fn contains(&self, event: &Event) -> bool {
+        let now = Utc::now().naive_utc();
+
+        event.time_end >= now - Duration::days(self.past_days) && event.time <= now + Duration::days(self.future_days)
+    }
+
+    fn filter(&self, events: Vec<Event>) -> Vec<Event> {
+        events
+            .into_iter()
+            .filter(|event| self.contains(event))
+            .collect()
+    }
+
+    /// The events matching `input` within the window, sorted by start time.
+    fn events(&self, client: &GraphQLClient, input: EventInput) -> error::Result<Vec<Event>> {
+        let now = Utc::now().naive_utc();
+        let from = now.checked_sub_signed(Duration::days(self.past_days)).unwrap_or_else(|| chrono::naive::MIN_DATE.and_hms(0, 0, 0));
+        let to = now.checked_add_signed(Duration::days(self.future_days + 1));
+
+        Ok(self.filter(get_events_running(client, from, to, input)?))
+    }
+}
+
+/// The id in an `<id>.ics` path segment.
+struct IcsId(i64);
+
+impl<'a> FromParam<'a> for IcsId {
+    type Error = &'a RawStr;
+
+    fn from_param(param: &'a RawStr) -> Result<IcsId, &'a RawStr> {
+        param
+            .as_str()
+            .strip_suffix(".ics")
+            .and_then(|id| id.parse().ok())
+            .map(IcsId)
+            .ok_or(param)
     }
 }
 
@@ -534,26 +1371,257 @@ struct DayEventContext {
     events: Vec<Event>,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Serialize)]
 struct LocationListTemplateContext<'a> {
     title: String,
     parent: &'a str,
-    page_id: u32,
+    timezone: String,
+    meta: PageMeta,
+    pagination: Paginator,
     locations: Vec<Location>,
+    flash: Option<String>,
+}
+
+#[derive(Serialize)]
+struct OrganizerListTemplateContext<'a> {
+    title: String,
+    parent: &'a str,
+    timezone: String,
+    meta: PageMeta,
+    pagination: Paginator,
+    organizers: Vec<Organizer>,
+    flash: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PriceFilter {
+    Free,
+    Paid,
+}
+
+/// The facets `/events` can be filtered by, taken from the query string.
+#[derive(Debug, Default)]
+struct EventFilter {
+    /// events with any of these tags
+    tags: Vec<i64>,
+    location: Option<i64>,
+    organizer: Option<i64>,
+    city: Option<String>,
+    price: Option<PriceFilter>,
+    min_price: Option<i64>,
+    max_price: Option<i64>,
+    currency: Option<String>,
+    /// first day in the visitor's timezone, without it only events which haven't ended are shown
+    from: Option<NaiveDate>,
+    /// last day in the visitor's timezone
+    to: Option<NaiveDate>,
+}
+
+impl<'f> FromForm<'f> for EventFilter {
+    type Error = String;
+
+    /// Empty values (of unused form fields) and unknown keys like `per_page` are ignored.
+    fn from_form(items: &mut FormItems<'f>, _strict: bool) -> Result<EventFilter, String> {
+        let mut filter = EventFilter::default();
+
+        for item in items {
+            let value = item.value.url_decode().map_err(|e| format!("{}: {}", item.key, e))?;
+            let value = value.trim();
+            if value.is_empty() {
+                continue;
+            }
+            let number = |value: &str| value.parse::<i64>().map_err(|e| format!("{}: {}", item.key, e));
+            let date = |value: &str| NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map_err(|e| format!("{}: {}", item.key, e))
+                .and_then(|date| if INPUT_YEARS.contains(&date.year()) {
+                    Ok(date)
+                } else {
+                    Err(format!("{}: year out of range", item.key))
+                });
+
+            match item.key.as_str() {
+                "tag" => filter.tags.push(number(value)?),
+                "location" => filter.location = Some(number(value)?),
+                "organizer" => filter.organizer = Some(number(value)?),
+                "city" => filter.city = Some(value.to_string()),
+                "price" => filter.price = match value {
+                    "free" => Some(PriceFilter::Free),
+                    "paid" => Some(PriceFilter::Paid),
+                    _ => return Err(format!("price: unknown value {:?}", value)),
+                },
+                "min_price" => filter.min_price = Some(number(value)?),
+                "max_price" => filter.max_price = Some(number(value)?),
+                "currency" => filter.currency = Some(value.to_string()),
+                "from" => filter.from = Some(date(value)?),
+                "to" => filter.to = Some(date(value)?),
+                _ => {}
+            }
+        }
+
+        Ok(filter)
+    }
+}
+
+impl EventFilter {
+    fn is_empty(&self) -> bool {
+        self.query().is_empty()
+    }
+
+    /// The filter as query string (without `?`), for links which keep it.
+    fn query(&self) -> String {
+        let mut parameters = self.tags
+            .iter()
+            .map(|tag| ("tag", tag.to_string()))
+            .collect::<Vec<(&str, String)>>();
+        parameters.extend(vec![
+            ("location", self.location.map(|id| id.to_string())),
+            ("organizer", self.organizer.map(|id| id.to_string())),
+            ("city", self.city.clone()),
+            ("price", self.price.map(|price| if price == PriceFilter::Free { "free" } else { "paid" }.to_string())),
+            ("min_price", self.min_price.map(|price| price.to_string())),
+            ("max_price", self.max_price.map(|price| price.to_string())),
+            ("currency", self.currency.clone()),
+            ("from", self.from.map(|date| date.format("%Y-%m-%d").to_string())),
+            ("to", self.to.map(|date| date.format("%Y-%m-%d").to_string())),
+        ]
+            .into_iter()
+            .filter_map(|(key, value)| value.map(|value| (key, value))));
+
+        parameters
+            .into_iter()
+            .map(|(key, value)| format!("{}={}", key, http::uri::Uri::percent_encode(&value)))
+            .collect::<Vec<String>>()
+            .join("&")
+    }
+
+    /// Start and end of the date range in UTC, `timezone` is the visitor's.
+    fn time_range(&self, timezone: Tz) -> (Option<NaiveDateTime>, Option<NaiveDateTime>) {
+        let local_midnight = |date: NaiveDate| calendar::local_to_utc(timezone, date.and_hms(0, 0, 0));
+
+        (self.from.and_then(local_midnight), self.to.and_then(|to| to.succ_opt()).and_then(local_midnight))
+    }
+
+    /// The events in the date range, without checking the other facets, sorted by start time.
+    fn events_in_range(&self, client: &GraphQLClient, timezone: Tz) -> error::Result<Vec<Event>> {
+        match self.time_range(timezone) {
+            (Some(start), end) => get_events_starting(client, Some(start), end, EventInput::default()),
+            (None, end) => get_events_running(client, Utc::now().naive_utc(), end, EventInput::default()),
+        }
+    }
+
+    /// Checks all facets, `timezone` is used for the date range.
+    fn matches(&self, event: &Event, timezone: Tz) -> bool {
+        let free = event.price.map_or(true, |price| price == 0);
+
+        let in_time_range = match self.time_range(timezone) {
+            (Some(start), end) => event.time >= start && end.map_or(true, |end| event.time < end),
+            (None, end) => event.time_end >= Utc::now().naive_utc() && end.map_or(true, |end| event.time < end),
+        };
+
+        in_time_range
+            && self.location.map_or(true, |id| event.location.id == id)
+            && self.organizer.map_or(true, |id| event.organizer.as_ref().map_or(false, |organizer| organizer.id == id))
+            && self.currency.as_ref().map_or(true, |currency| event.currency.as_ref().map_or(false, |c| c.eq_ignore_ascii_case(currency)))
+            && (self.tags.is_empty() || event.tags.iter().any(|tag| self.tags.contains(&tag.id)))
+            && self.city.as_ref().map_or(true, |city| event.location.city.eq_ignore_ascii_case(city))
+            && self.price.map_or(true, |price| (price == PriceFilter::Free) == free)
+            && self.min_price.map_or(true, |min| event.price.unwrap_or(0) >= min)
+            && self.max_price.map_or(true, |max| event.price.unwrap_or(0) <= max)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct FacetOption {
+    value: String,
+    name: String,
+    selected: bool,
+}
+
+/// The values of the filter form, the options are collected from the events in the filter's date range.
+#[derive(Debug, Serialize)]
+struct EventFacets {
+    active: bool,
+    tags: Vec<FacetOption>,
+    locations: Vec<FacetOption>,
+    organizers: Vec<FacetOption>,
+    cities: Vec<FacetOption>,
+    currencies: Vec<FacetOption>,
+    free: bool,
+    paid: bool,
+    min_price: Option<i64>,
+    max_price: Option<i64>,
+    from: Option<String>,
+    to: Option<String>,
+}
+
+impl EventFacets {
+    fn new(events: &[Event], filter: &EventFilter) -> EventFacets {
+        fn options(values: impl Iterator<Item=(String, String)>, selected: impl Fn(&str) -> bool) -> Vec<FacetOption> {
+            let mut options = values
+                .collect::<HashMap<String, String>>()
+                .into_iter()
+                .map(|(value, name)| FacetOption {
+                    selected: selected(&value),
+                    value,
+                    name,
+                })
+                .collect::<Vec<FacetOption>>();
+            options.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+            options
+        }
+        let selected_id = |id: Option<i64>| move |value: &str| id.map(|id| id.to_string()) == Some(value.to_string());
+        let selected_text = |text: &Option<String>| {
+            let text = text.clone();
+            move |value: &str| text.as_deref().map_or(false, |text| text.eq_ignore_ascii_case(value))
+        };
+
+        EventFacets {
+            active: !filter.is_empty(),
+            tags: options(
+                events.iter().flat_map(|event| event.tags.iter().map(|tag| (tag.id.to_string(), tag.name.clone()))),
+                |value| filter.tags.iter().any(|tag| tag.to_string() == value),
+            ),
+            locations: options(
+                events.iter().map(|event| (event.location.id.to_string(), event.location.name.clone())),
+                selected_id(filter.location),
+            ),
+            organizers: options(
+                events.iter().filter_map(|event| event.organizer.as_ref()).map(|organizer| (organizer.id.to_string(), organizer.name.clone())),
+                selected_id(filter.organizer),
+            ),
+            cities: options(
+                events.iter().map(|event| (event.location.city.clone(), event.location.city.clone())),
+                selected_text(&filter.city),
+            ),
+            currencies: options(
+                events.iter().filter_map(|event| event.currency.clone()).map(|currency| (currency.clone(), currency)),
+                selected_text(&filter.currency),
+            ),
+            free: filter.price == Some(PriceFilter::Free),
+            paid: filter.price == Some(PriceFilter::Paid),
+            min_price: filter.min_price,
+            max_price: filter.max_price,
+            from: filter.from.map(|date| date.format("%Y-%m-%d").to_string()),
+            to: filter.to.map(|date| date.format("%Y-%m-%d").to_string()),
+        }
+    }
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Serialize)]
 struct EventListTemplateContext<'a> {
     title: String,
     parent: &'a str,
-    page_id: u32,
+    timezone: String,
+    meta: PageMeta,
+    pagination: Paginator,
+    facets: EventFacets,
     days: Vec<DayEventContext>,
     flash: Option<String>,
 }
 
 
-fn get_event(id: i64) -> io::Result<Event> {
-    get_events(EventInput {
+fn get_event(client: &GraphQLClient, id: i64) -> error::Result<Event> {
+    get_events(client, EventInput {
         id: Some(id),
         name: None,
         description: None,
@@ -565,25 +1633,36 @@ fn get_event(id: i64) -> io::Result<Event> {
         organizer_id: None,
     }.into())?
         .pop()
-        .ok_or(io::Error::from(io::ErrorKind::NotFound))
+        .ok_or(Error::NotFound)
 }
 
-fn get_events_day_contexts(pagination: PaginationContext) -> io::Result<Vec<DayEventContext>> {
-    let events = get_events(event::Variables {
-        constraints: Some(event::Constraints {
-            offset: pagination.offset.to_string(),
-            limit: pagination.limit.to_string(),
-        }),
-        input: None,
-    })?;
+/// The entries of page `pagination` of a list which was fetched as a whole, see `get_all_events`.
+fn page_of<T>(entries: Vec<T>, pagination: &PaginationContext) -> Vec<T> {
+    entries
+        .into_iter()
+        .skip(pagination.offset as usize)
+        .take(pagination.limit as usize)
+        .collect()
+}
+
+/// Events starting in `period` in `timezone`, sorted by start time.
+fn get_events_in_period(client: &GraphQLClient, timezone: Tz, period: Period) -> error::Result<Vec<Event>> {
+    let local_midnight = |date: NaiveDate| calendar::local_to_utc(timezone, date.and_hms(0, 0, 0));
+    match (local_midnight(period.first_day()), local_midnight(period.end())) {
+        (Some(start), Some(end)) => get_events_starting(client, Some(start), Some(end), EventInput::default()),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Groups the events by the day they start on in `timezone`, `day` is the UTC time of its local midnight.
+fn group_by_day(events: Vec<Event>, timezone: Tz) -> Vec<DayEventContext> {
     let mut map: HashMap<i64, Vec<Event>> = HashMap::new();
 
     events
         .into_iter()
         .for_each(|event| {
-            let date = NaiveDate::from_ymd(event.time.year(), event.time.month(), event.time.day());
-            let time = NaiveTime::from_hms(0, 0, 0);
-            let start_of_day = NaiveDateTime::new(date, time);
+            let date = timezone.from_utc_datetime(&event.time).date().naive_local();
+            let start_of_day = calendar::local_to_utc(timezone, date.and_hms(0, 0, 0)).unwrap_or(event.time);
             let day_timestamp = start_of_day.timestamp();
 
             match map.get_mut(&day_timestamp) {
@@ -604,203 +1683,1722 @@ fn get_events_day_contexts(pagination: PaginationContext) -> io::Result<Vec<DayE
         })
         .collect::<Vec<DayEventContext>>();
     result.sort_by(|a: &DayEventContext, b: &DayEventContext| a.day.timestamp().cmp(&b.day.timestamp()));
+    for day in &mut result {
+        day.events.sort_by_key(|event| event.time);
+    }
 
-    Ok(result)
+    result
 }
 
-#[get("/")]
-fn index(flash: Option<FlashMessage<'_, '_>>) -> Redirect {
-    Redirect::to("/events/1") // there has to be a better option
+/// One page of a listing with its navigation, shared by the HTML pages and the JSON API.
+///
+/// `prefix` is put in front of the page links, e.g. `/api/v1`.
+struct ListPage<T> {
+    entries: Vec<T>,
+    paginator: Paginator,
 }
 
-#[get("/locations")]
-fn locations(flash: Option<FlashMessage<'_, '_>>) -> Redirect {
-    Redirect::to("/locations/1") // there has to be a better option
+fn location_page(client: &GraphQLClient, page: u32, per_page: PerPage, prefix: &str) -> error::Result<ListPage<Location>> {
+    let pagination = PaginationContext::page(page, per_page.limit);
+
+    let mut input: location::Variables = LocationInput::default().into();
+    input.constraints = Some(location::Constraints {
+        limit: pagination.limit.to_string(),
+        offset: pagination.offset.to_string(),
+    });
+    let entries = get_locations(client, input)?;
+    let total = get_pagination(client)?.location_count;
+
+    Ok(ListPage {
+        entries,
+        paginator: Paginator::new(page, pagination.limit, total, &|page| per_page.link(&format!("{}/locations/{}", prefix, page))),
+    })
 }
 
-#[get("/locations/<id>")]
-fn locations_numbered(id: Option<u32>) -> Template {
-    let page_id = max(1, id.unwrap_or(1));
-    let pagination = if page_id > 1 {
-        let mut context = PaginationContext::default();
-        context.offset = context.limit * (page_id - 1);
-        context
-    } else {
-        PaginationContext::default()
-    };
+fn organizer_page(client: &GraphQLClient, page: u32, per_page: PerPage, prefix: &str) -> error::Result<ListPage<Organizer>> {
+    let pagination = PaginationContext::page(page, per_page.limit);
 
-    let mut input: location::Variables = LocationInput {
-        id: None,
-        name: None,
-        website: None,
-        street: None,
-        street_number: None,
-        city: None,
-        postal_code: None,
-        country: None,
-        building: None,
-        maps_link: None,
-    }.into();
-    input.constraints = Some(location::Constraints {
+    let mut input: organizer::Variables = OrganizerInput::default().into();
+    input.constraints = Some(organizer::Constraints {
         limit: pagination.limit.to_string(),
         offset: pagination.offset.to_string(),
     });
-    let locations = get_locations(input).unwrap(); // TODO
+    let entries = get_organizers(client, input)?;
+    let total = get_pagination(client)?.organizer_count;
 
-    Template::render("locations", LocationListTemplateContext {
-        title: "Locations".to_string(),
-        parent: "layout",
-        page_id,
-        locations,
+    Ok(ListPage {
+        entries,
+        paginator: Paginator::new(page, pagination.limit, total, &|page| per_page.link(&format!("{}/organizers/{}", prefix, page))),
     })
 }
 
-#[get("/events/<id>")]
-fn numbered_index(id: Option<u32>, flash: Option<FlashMessage<'_, '_>>) -> Template {
-    let page_id = max(1, id.unwrap_or(1));
-    let pagination = if page_id > 1 {
-        let mut context = PaginationContext::default();
-        context.offset = context.limit * (page_id - 1);
-        context
+/// Page `page` of the `events` matching `filter`, the events are the ones of `EventFilter::events_in_range`.
+fn event_page(events: Vec<Event>, timezone: Tz, page: u32, filter: &EventFilter, per_page: PerPage, prefix: &str) -> ListPage<DayEventContext> {
+    let pagination = PaginationContext::page(page, per_page.limit);
+    let events = events
+        .into_iter()
+        .filter(|event| filter.matches(event, timezone))
+        .collect::<Vec<Event>>();
+    let total = events.len() as i64;
+    let query = filter.query();
+    let link = |page: u32| if query.is_empty() {
+        per_page.link(&format!("{}/events/{}", prefix, page))
     } else {
-        PaginationContext::default()
-    };
-    let days = get_events_day_contexts(pagination).unwrap(); // TODO
-
-    let context = EventListTemplateContext {
-        title: "Events".to_string(),
-        parent: "layout",
-        page_id,
-        days,
-        flash: flash.and_then(|f| Some(f.msg().to_string())),
+        per_page.link(&format!("{}/events/{}?{}", prefix, page, query))
     };
 
-    Template::render("index", context)
+    ListPage {
+        entries: group_by_day(page_of(events, &pagination), timezone),
+        paginator: Paginator::new(page, pagination.limit, total, &link),
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-struct EventTemplateContext<'a> {
-    title: String,
-    parent: &'a str,
-    event: Event,
-}
+/// Tags sorted by name or by their number of upcoming and past events.
+fn tag_page(client: &GraphQLClient, sort_by_name: bool, page: u32, per_page: PerPage, prefix: &str) -> error::Result<ListPage<TagSummary>> {
+    let mut tags = get_all_tags(client)?
+        .into_iter()
+        .map(TagSummary::from)
+        .collect::<Vec<TagSummary>>();
 
-#[derive(Debug, Deserialize, Serialize)]
-struct LocationTemplateContext<'a> {
-    title: String,
-    parent: &'a str,
-    location: Location,
-    events: Vec<Event>,
-}
+    let by_name = |a: &TagSummary, b: &TagSummary| a.name.to_lowercase().cmp(&b.name.to_lowercase());
+    if sort_by_name {
+        tags.sort_by(by_name);
+    } else {
+        tags.sort_by(|a, b|
+            (b.upcoming_events, b.past_events)
+                .cmp(&(a.upcoming_events, a.past_events))
+                .then_with(|| by_name(a, b)));
+    }
 
-#[derive(Debug, Deserialize, Serialize)]
-struct TagTemplateContext<'a> {
-    title: String,
-    parent: &'a str,
-    tag: Tag,
+    let pagination = PaginationContext::page(page, per_page.limit);
+    let total = tags.len() as i64;
+    let sort = if sort_by_name { "name" } else { "popularity" };
+
+    Ok(ListPage {
+        entries: page_of(tags, &pagination),
+        paginator: Paginator::new(page, pagination.limit, total, &|page| per_page.link(&format!("{}/tags?sort={}&page={}", prefix, sort, page))),
+    })
 }
 
-#[get("/event/<id>")]
-fn event(id: i64) -> Template {
-    let event = get_event(id).unwrap(); // TODO
-    let context = EventTemplateContext {
-        title: event.name.clone(),
-        parent: "layout",
-        event,
-    };
+#[get("/")]
+fn index() -> Redirect {
+    Redirect::to("/events/1") // there has to be a better option
+}
 
-    Template::render("event", context)
+#[get("/locations")]
+fn locations() -> Redirect {
+    Redirect::to("/locations/1") // there has to be a better option
 }
 
-#[get("/event/<id>/ics")]
-fn event_ics(id: i64) -> io::Result<Content<Stream<Cursor<Vec<u8>>>>> {
-    let mut buffer = Vec::new();
-    get_event(id)?.get_ics().write(&mut buffer)?;
-    let cursor = Cursor::new(buffer);
+#[get("/locations/<id>")]
+fn locations_numbered(client: GraphQLClient, timezone: VisitorTimezone, meta: PageMeta, id: Option<u32>, per_page: PerPage, flash: Option<FlashMessage<'_, '_>>) -> error::Result<Template> {
+    let page = location_page(&client, max(1, id.unwrap_or(1)), per_page, "")?;
 
-    Ok(Content(rocket::http::ContentType::Calendar, Stream::from(cursor)))
+    Ok(Template::render("locations", LocationListTemplateContext {
+        title: "Locations".to_string(),
+        parent: "layout",
+        timezone: timezone.name(),
+        meta,
+        pagination: page.paginator,
+        locations: page.entries,
+        flash: flash_message(&client, timezone, flash),
+    }))
 }
 
-#[get("/event/<id>/location")]
-fn event_location(id: i64) -> io::Result<Redirect> {
-    Ok(Redirect::permanent(format!("/location/{}", get_event(id)?.location.id)))
+#[get("/organizers")]
+fn organizers() -> Redirect {
+    Redirect::to("/organizers/1")
 }
 
-#[get("/tag/<id>")]
-fn tag(id: i64) -> Template {
-    let tag = get_tag(id).unwrap(); // TODO
+#[get("/organizers/<id>")]
+fn organizers_numbered(client: GraphQLClient, timezone: VisitorTimezone, meta: PageMeta, id: Option<u32>, per_page: PerPage, flash: Option<FlashMessage<'_, '_>>) -> error::Result<Template> {
+    let page = organizer_page(&client, max(1, id.unwrap_or(1)), per_page, "")?;
 
-    let context = TagTemplateContext {
-        title: tag.name.clone(),
+    Ok(Template::render("organizers", OrganizerListTemplateContext {
+        title: "Organizers".to_string(),
         parent: "layout",
-        tag,
+        timezone: timezone.name(),
+        meta,
+        pagination: page.paginator,
+        organizers: page.entries,
+        flash: flash_message(&client, timezone, flash),
+    }))
+}
+
+#[get("/events/<id>?<filter..>")]
+fn numbered_index(client: GraphQLClient, timezone: VisitorTimezone, meta: PageMeta, id: Option<u32>, filter: Form<EventFilter>, per_page: PerPage, flash: Option<FlashMessage<'_, '_>>) -> error::Result<Template> {
+    let events = filter.events_in_range(&client, timezone.0)?;
+    let facets = EventFacets::new(&events, &filter);
+    let page = event_page(events, timezone.0, max(1, id.unwrap_or(1)), &filter, per_page, "");
+
+    let context = EventListTemplateContext {
+        title: "Events".to_string(),
+        parent: "layout",
+        timezone: timezone.name(),
+        meta,
+        pagination: page.paginator,
+        facets,
+        days: page.entries,
+        flash: flash_message(&client, timezone, flash),
+    };
+
+    Ok(Template::render("index", context))
+}
+
+/// Number of characters of a description shown with a search result.
+const SEARCH_SNIPPET_LENGTH: usize = 200;
+
+/// `title` and `snippet` are escaped HTML with the matched terms highlighted.
+#[derive(Serialize)]
+struct SearchResult {
+    href: String,
+    title: String,
+    snippet: Option<String>,
+    detail: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SearchGroup {
+    name: &'static str,
+    results: Vec<SearchResult>,
+}
+
+#[derive(Serialize)]
+struct SearchTemplateContext<'a> {
+    title: String,
+    parent: &'a str,
+    timezone: String,
+    meta: PageMeta,
+    query: String,
+    result_count: usize,
+    groups: Vec<SearchGroup>,
+    flash: Option<String>,
+}
+
+/// Finds events, locations, organizers and tags containing all words of `q` in their name or description.
+#[get("/search?<q>")]
+fn search(client: GraphQLClient, timezone: VisitorTimezone, meta: PageMeta, q: Option<String>, flash: Option<FlashMessage<'_, '_>>) -> error::Result<Template> {
+    let query = q.unwrap_or_default();
+    let terms = search::terms(&query);
+    let mut groups = Vec::new();
+
+    if !terms.is_empty() {
+        let snippet = |text: &str| Some(search::snippet(text, &terms, SEARCH_SNIPPET_LENGTH)).filter(|snippet| !snippet.is_empty());
+
+        let mut events = get_all_events(&client)?
+            .into_iter()
+            .filter(|event| search::matches(&terms, &[&event.name, &event.description]))
+            .collect::<Vec<Event>>();
+        // upcoming events first, then the most recent past ones
+        let now = Utc::now().naive_utc();
+        events.sort_by_key(|event| if event.time_end < now { (1, -event.time.timestamp()) } else { (0, event.time.timestamp()) });
+        groups.push(SearchGroup {
+            name: "Events",
+            results: events
+                .into_iter()
+                .map(|event| SearchResult {
+                    href: format!("/event/{}", event.id),
+                    title: search::highlight(&event.name, &terms),
+                    snippet: snippet(&event.description),
+                    detail: Some(format!("{} · {}", timezone.0.from_utc_datetime(&event.time).format("%d.%m.%Y %H:%M"), search::escape_html(&event.location.name))),
+                })
+                .collect(),
+        });
+
+        groups.push(SearchGroup {
+            name: "Locations",
+            results: get_all_locations(&client)?
+                .into_iter()
+                .filter(|location| search::matches(&terms, &[&location.name, &location.street, &location.city, location.building.as_deref().unwrap_or_default()]))
+                .map(|location| SearchResult {
+                    href: format!("/location/{}", location.id),
+                    title: search::highlight(&location.name, &terms),
+                    snippet: None,
+                    detail: Some(search::highlight(&format!("{} {}, {} {}", location.street, location.street_number, location.postal_code, location.city), &terms)),
+                })
+                .collect(),
+        });
+
+        groups.push(SearchGroup {
+            name: "Organizers",
+            results: get_all_organizers(&client)?
+                .into_iter()
+                .filter(|organizer| search::matches(&terms, &[&organizer.name]))
+                .map(|organizer| SearchResult {
+                    href: format!("/organizer/{}", organizer.id),
+                    title: search::highlight(&organizer.name, &terms),
+                    snippet: None,
+                    detail: organizer.website.as_deref().map(search::escape_html),
+                })
+                .collect(),
+        });
+
+        groups.push(SearchGroup {
+            name: "Tags",
+            results: get_all_tags(&client)?
+                .into_iter()
+                .filter(|tag| search::matches(&terms, &[&tag.name, tag.description.as_deref().unwrap_or_default()]))
+                .map(|tag| SearchResult {
+                    href: format!("/tag/{}", tag.id),
+                    title: search::highlight(&tag.name, &terms),
+                    snippet: tag.description.as_deref().and_then(snippet),
+                    detail: None,
+                })
+                .collect(),
+        });
+    }
+
+    groups.retain(|group| !group.results.is_empty());
+
+    Ok(Template::render("search", SearchTemplateContext {
+        title: if query.trim().is_empty() { "Search".to_string() } else { format!("Search: {}", query.trim()) },
+        parent: "layout",
+        timezone: timezone.name(),
+        meta,
+        result_count: groups.iter().map(|group| group.results.len()).sum(),
+        query,
+        groups,
+        flash: flash_message(&client, timezone, flash),
+    }))
+}
+
+#[derive(Serialize)]
+struct ArchiveMonthContext {
+    title: String,
+    href: String,
+    days: Vec<DayEventContext>,
+}
+
+#[derive(Serialize)]
+struct ArchiveTemplateContext<'a> {
+    title: String,
+    parent: &'a str,
+    timezone: String,
+    meta: PageMeta,
+    /// the months shown on the page, e.g. "April 2021 - June 2021"
+    range: String,
+    newer: Option<String>,
+    older: Option<String>,
+    months: Vec<ArchiveMonthContext>,
+    flash: Option<String>,
+}
+
+/// Months of past events shown on each page of the archive.
+const ARCHIVE_MONTHS: u32 = 3;
+
+/// Past events, the most recent first, grouped by the month they started in.
+///
+/// The first page shows the current month and the ones before it, each further page goes `ARCHIVE_MONTHS` months back.
+#[get("/archive?<page>")]
+fn archive(client: GraphQLClient, timezone: VisitorTimezone, meta: PageMeta, page: Option<u32>, flash: Option<FlashMessage<'_, '_>>) -> error::Result<Template> {
+    let page = max(1, page.unwrap_or(1));
+    let today = today(&timezone);
+    let newest = Period::months_before(today, (page - 1).saturating_mul(ARCHIVE_MONTHS)).ok_or(Error::NotFound)?;
+    let older = Period::months_before(today, page.saturating_mul(ARCHIVE_MONTHS));
+    let oldest = older.map_or(Period::Month(1, 1), |older| older.next());
+
+    let now = Utc::now().naive_utc();
+    let local_midnight = |date: NaiveDate| calendar::local_to_utc(timezone.0, date.and_hms(0, 0, 0));
+    let to = local_midnight(newest.end()).map_or(now, |end| end.min(now));
+    let mut events = get_events_starting(&client, local_midnight(oldest.first_day()), Some(to), EventInput::default())?;
+    events.retain(|event| event.time_end < now);
+
+    let mut months: Vec<ArchiveMonthContext> = Vec::new();
+    for mut day in group_by_day(events, timezone.0).into_iter().rev() {
+        day.events.reverse();
+        let month = Period::month_of(timezone.0.from_utc_datetime(&day.day).date().naive_local());
+        match months.last_mut() {
+            Some(last) if last.href == month.path() => last.days.push(day),
+            _ => months.push(ArchiveMonthContext {
+                title: month.title(),
+                href: month.path(),
+                days: vec![day],
+            }),
+        }
+    }
+
+    Ok(Template::render("archive", ArchiveTemplateContext {
+        title: "Archive".to_string(),
+        parent: "layout",
+        timezone: timezone.name(),
+        meta,
+        range: if oldest == newest { newest.title() } else { format!("{} - {}", oldest.title(), newest.title()) },
+        newer: Some(page - 1).filter(|page| *page >= 1).map(|page| format!("/archive?page={}", page)),
+        older: older.map(|_| format!("/archive?page={}", page + 1)),
+        months,
+        flash: flash_message(&client, timezone, flash),
+    }))
+}
+
+#[derive(Debug, Serialize)]
+struct PeriodLink {
+    title: String,
+    href: String,
+}
+
+#[derive(Serialize)]
+struct EventPeriodTemplateContext<'a> {
+    title: String,
+    parent: &'a str,
+    timezone: String,
+    meta: PageMeta,
+    previous: PeriodLink,
+    next: PeriodLink,
+    day: String,
+    week: String,
+    month: String,
+    days: Vec<DayEventContext>,
+    flash: Option<String>,
+}
+
+fn render_period(client: &GraphQLClient, timezone: VisitorTimezone, meta: PageMeta, period: Option<Period>, flash: Option<FlashMessage<'_, '_>>) -> error::Result<Template> {
+    let period = period.ok_or(Error::NotFound)?;
+    let days = group_by_day(get_events_in_period(client, timezone.0, period)?, timezone.0);
+    let link = |period: Period| PeriodLink {
+        title: period.title(),
+        href: period.path(),
+    };
+    let first_day = period.first_day();
+
+    Ok(Template::render("events_period", EventPeriodTemplateContext {
+        title: period.title(),
+        parent: "layout",
+        timezone: timezone.name(),
+        meta,
+        previous: link(period.previous()),
+        next: link(period.next()),
+        day: Period::Day(first_day).path(),
+        week: Period::week_of(first_day).path(),
+        month: Period::month_of(first_day).path(),
+        days,
+        flash: flash_message(client, timezone, flash),
+    }))
+}
+
+/// The date in the visitor's timezone, for the views of the current day/week/month.
+fn today(timezone: &VisitorTimezone) -> NaiveDate {
+    timezone.0.from_utc_datetime(&Utc::now().naive_utc()).date().naive_local()
+}
+
+#[get("/events/day")]
+fn events_today(timezone: VisitorTimezone) -> Redirect {
+    Redirect::to(Period::Day(today(&timezone)).path())
+}
+
+#[get("/events/week")]
+fn events_this_week(timezone: VisitorTimezone) -> Redirect {
+    Redirect::to(Period::week_of(today(&timezone)).path())
+}
+
+#[get("/events/month")]
+fn events_this_month(timezone: VisitorTimezone) -> Redirect {
+    Redirect::to(Period::month_of(today(&timezone)).path())
+}
+
+#[get("/events/day/<date>")]
+fn events_day(client: GraphQLClient, timezone: VisitorTimezone, meta: PageMeta, date: &RawStr, flash: Option<FlashMessage<'_, '_>>) -> error::Result<Template> {
+    render_period(&client, timezone, meta, Period::day(date), flash)
+}
+
+#[get("/events/week/<week>")]
+fn events_week(client: GraphQLClient, timezone: VisitorTimezone, meta: PageMeta, week: &RawStr, flash: Option<FlashMessage<'_, '_>>) -> error::Result<Template> {
+    render_period(&client, timezone, meta, Period::week(week), flash)
+}
+
+#[get("/events/month/<month>")]
+fn events_month(client: GraphQLClient, timezone: VisitorTimezone, meta: PageMeta, month: &RawStr, flash: Option<FlashMessage<'_, '_>>) -> error::Result<Template> {
+    render_period(&client, timezone, meta, Period::month(month), flash)
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct EventTemplateContext<'a> {
+    title: String,
+    parent: &'a str,
+    timezone: String,
+    meta: PageMeta,
+    /// schema.org data of the page
+    json_ld: String,
+    event: Event,
+    flash: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct SelectOption {
+    id: i64,
+    name: String,
+    selected: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct EventEditTemplateContext<'a> {
+    title: String,
+    parent: &'a str,
+    timezone: String,
+    meta: PageMeta,
+    id: i64,
+    event: EventForm,
+    errors: FieldErrors,
+    locations: Vec<SelectOption>,
+    organizers: Vec<SelectOption>,
+    tags: Vec<SelectOption>,
+    flash: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct LocationTemplateContext<'a> {
+    title: String,
+    parent: &'a str,
+    timezone: String,
+    meta: PageMeta,
+    /// schema.org data of the page
+    json_ld: String,
+    /// path the RSS/Atom feeds of the page are below
+    feed: String,
+    location: Location,
+    events: Vec<Event>,
+    flash: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct LocationEditTemplateContext<'a> {
+    title: String,
+    parent: &'a str,
+    timezone: String,
+    meta: PageMeta,
+    id: i64,
+    location: LocationForm,
+    errors: FieldErrors,
+    flash: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct DeleteTemplateContext<'a> {
+    title: String,
+    parent: &'a str,
+    timezone: String,
+    meta: PageMeta,
+    kind: &'a str,
+    name: String,
+    action: String,
+    cancel: String,
+    /// events which still reference the entity and block its deletion
+    events: Vec<Event>,
+    /// possible targets to move the blocking events to
+    locations: Vec<SelectOption>,
+    flash: Option<String>,
+}
+
+#[derive(FromForm)]
+struct DeleteForm {
+    reassign_to: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct OrganizerTemplateContext<'a> {
+    title: String,
+    parent: &'a str,
+    timezone: String,
+    meta: PageMeta,
+    /// path the RSS/Atom feeds of the page are below
+    feed: String,
+    organizer: Organizer,
+    events: Vec<Event>,
+    flash: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct TagSummary {
+    id: i64,
+    name: String,
+    description: Option<String>,
+    upcoming_events: usize,
+    past_events: usize,
+}
+
+impl From<Tag> for TagSummary {
+    fn from(tag: Tag) -> TagSummary {
+        let now = Utc::now().naive_utc();
+        let upcoming_events = tag.events.iter().filter(|event| event.time_end >= now).count();
+
+        TagSummary {
+            id: tag.id,
+            name: tag.name,
+            description: tag.description,
+            upcoming_events,
+            past_events: tag.events.len() - upcoming_events,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct TagListTemplateContext<'a> {
+    title: String,
+    parent: &'a str,
+    timezone: String,
+    meta: PageMeta,
+    sort_by_name: bool,
+    pagination: Paginator,
+    tags: Vec<TagSummary>,
+    flash: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct TagTemplateContext<'a> {
+    title: String,
+    parent: &'a str,
+    timezone: String,
+    meta: PageMeta,
+    /// path the RSS/Atom feeds of the page are below
+    feed: String,
+    tag: Tag,
+    flash: Option<String>,
+}
+
+/// The flash message of the previous request, preceded by a notice if the page shows cached data because the backend is down.
+fn flash_message(client: &GraphQLClient, timezone: VisitorTimezone, flash: Option<FlashMessage<'_, '_>>) -> Option<String> {
+    let notice = client.stale_since().map(|since|
+        format!("The event backend is unavailable, showing data as of {}", since.with_timezone(&timezone.0).format("%H:%M")));
+    let flash = flash.map(|f| f.msg().to_string());
+
+    match (notice, flash) {
+        (Some(notice), Some(flash)) => Some(format!("{} - {}", notice, flash)),
+        (notice, flash) => notice.or(flash),
+    }
+}
+
+#[get("/event/<id>")]
+fn event(client: GraphQLClient, site: State<Site>, timezone: VisitorTimezone, meta: PageMeta, id: i64, flash: Option<FlashMessage<'_, '_>>) -> error::Result<Template> {
+    let event = get_event(&client, id)?;
+    let context = EventTemplateContext {
+        title: event.name.clone(),
+        parent: "layout",
+        timezone: timezone.name(),
+        meta: meta
+            .describe(&format!("{}. {}", event.share_details(site.timezone).join(", "), markdown::plain_text(&event.description)))
+            .with_image(site.url(&format!("/event/{}/og.png", event.id))),
+        json_ld: json_ld_script(event.json_ld(&site)),
+        event,
+        flash: flash_message(&client, timezone, flash),
+    };
+
+    Ok(Template::render("event", context))
+}
+
+/// How long crawlers may show a share image without asking whether it changed, in seconds.
+const SHARE_IMAGE_MAX_AGE: u32 = 3600;
+
+/// The entity tags of the `If-None-Match` header.
+struct IfNoneMatch(Vec<String>);
+
+impl IfNoneMatch {
+    fn matches(&self, etag: &str) -> bool {
+        self.0.iter().any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for IfNoneMatch {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<IfNoneMatch, ()> {
+        request::Outcome::Success(IfNoneMatch(request
+            .headers()
+            .get("If-None-Match")
+            .flat_map(|value| value.split(','))
+            .map(|tag| tag.trim().to_string())
+            .collect()))
+    }
+}
+
+/// A PNG which may be cached, `png` is `None` if the requester's copy with the same `etag` is still current.
+struct CachedPng {
+    etag: String,
+    png: Option<Vec<u8>>,
+}
+
+impl<'r> Responder<'r> for CachedPng {
+    fn respond_to(self, _: &Request) -> response::Result<'r> {
+        let mut response = Response::build();
+        response
+            .raw_header("ETag", self.etag)
+            .raw_header("Cache-Control", format!("public, max-age={}", SHARE_IMAGE_MAX_AGE));
+        match self.png {
+            Some(png) => response.header(http::ContentType::PNG).sized_body(Cursor::new(png)),
+            None => response.status(http::Status::NotModified),
+        };
+
+        response.ok()
+    }
+}
+
+/// The image shown when a link to the event is shared, with the times in the site's timezone.
+#[get("/event/<id>/og.png")]
+fn event_share_image(client: GraphQLClient, site: State<Site>, renderer: State<share_image::Renderer>, if_none_match: IfNoneMatch, id: i64) -> error::Result<CachedPng> {
+    let event = get_event(&client, id)?;
+    let details = event.share_details(site.timezone);
+    let etag = share_image::etag(&event.name, &details);
+
+    let png = if if_none_match.matches(&etag) {
+        None
+    } else {
+        Some(renderer.render(&event.name, &details)?)
+    };
+
+    Ok(CachedPng { etag, png })
+}
+
+#[get("/event/<id>/edit")]
+fn event_edit(client: GraphQLClient, timezone: VisitorTimezone, meta: PageMeta, id: i64, flash: Option<FlashMessage<'_, '_>>) -> error::Result<Template> {
+    let event = if id != 0 {
+        EventForm::from_event(get_event(&client, id)?, timezone.0)
+    } else {
+        // today at 20:00 for the visitor
+        let evening = Utc::now().with_timezone(&timezone.0).date().naive_local().and_hms(20, 0, 0).format("%Y-%m-%dT%H:%M").to_string();
+        EventForm {
+            time: evening.clone(),
+            time_end: evening,
+            ..EventForm::default()
+        }
+    };
+    let flash = flash_message(&client, timezone, flash);
+
+    render_event_form(&client, timezone, meta, id, event, FieldErrors::new(), flash)
+}
+
+fn render_event_form(client: &GraphQLClient, timezone: VisitorTimezone, meta: PageMeta, id: i64, event: EventForm, errors: FieldErrors, flash: Option<String>) -> error::Result<Template> {
+    let locations = get_all_locations(client)?
+        .into_iter()
+        .map(|location| SelectOption {
+            selected: event.location_id.trim() == location.id.to_string(),
+            id: location.id,
+            name: location.name,
+        })
+        .collect();
+    let organizers = get_all_organizers(client)?
+        .into_iter()
+        .map(|organizer| SelectOption {
+            selected: event.organizer_id.trim() == organizer.id.to_string(),
+            id: organizer.id,
+            name: organizer.name,
+        })
+        .collect();
+    let tags = get_all_tags(client)?
+        .into_iter()
+        .map(|tag| SelectOption {
+            selected: event.tags.iter().any(|t| t.trim() == tag.id.to_string()),
+            id: tag.id,
+            name: tag.name,
+        })
+        .collect();
+
+    let context = EventEditTemplateContext {
+        title: if id != 0 { event.name.clone() } else { "New event".to_string() },
+        parent: "layout",
+        timezone: timezone.name(),
+        meta,
+        id,
+        event,
+        errors,
+        locations,
+        organizers,
+        tags,
+        flash,
+    };
+
+    Ok(Template::render("edit/event", context))
+}
+
+#[post("/event/<id>/submit", data = "<event>")]
+fn event_submit(client: GraphQLClient, timezone: VisitorTimezone, meta: PageMeta, id: i64, event: Form<EventForm>) -> error::Result<Result<Flash<Redirect>, Template>> {
+    let form = event.into_inner();
+    let mutation = match form.validate().and_then(|mutation| mutation.with_local_times(timezone.0)) {
+        Ok(mutation) => mutation,
+        Err(errors) => return render_event_form(&client, timezone, meta, id, form, errors, None).map(Err),
+    };
+    let result = if id != 0 {
+        mutation.update(&client, id)
+    } else {
+        mutation.add(&client)
+    };
+
+    match result {
+        Ok(event_id) => Ok(Ok(Flash::success(Redirect::to(format!("/event/{}", event_id)), "Event saved"))),
+        Err(e) => render_event_form(&client, timezone, meta, id, form, FieldErrors::new(), Some(format!("Couldn't save event: {}", e))).map(Err),
+    }
+}
+
+#[get("/event/<id>/ics")]
+fn event_ics(client: GraphQLClient, site: State<Site>, timezone: VisitorTimezone, id: i64) -> error::Result<Content<Stream<Cursor<Vec<u8>>>>> {
+    ics_response(get_event(&client, id)?.get_ics(&site, timezone.0))
+}
+
+#[get("/events.ics?<past_days>&<future_days>")]
+fn events_ics(client: GraphQLClient, site: State<Site>, timezone: VisitorTimezone, window: State<FeedWindow>, past_days: Option<u32>, future_days: Option<u32>) -> error::Result<Content<Stream<Cursor<Vec<u8>>>>> {
+    let events = window.with(past_days, future_days).events(&client, EventInput::default())?;
+
+    ics_response(ics_calendar(&site, timezone.0, Some("Events".to_string()), &events))
+}
+
+#[get("/tag/<id>?<past_days>&<future_days>", rank = 2)]
+fn tag_ics(client: GraphQLClient, site: State<Site>, timezone: VisitorTimezone, window: State<FeedWindow>, id: IcsId, past_days: Option<u32>, future_days: Option<u32>) -> error::Result<Content<Stream<Cursor<Vec<u8>>>>> {
+    let tag = get_tag(&client, id.0)?;
+    let events = window.with(past_days, future_days).filter(tag.events);
+
+    ics_response(ics_calendar(&site, timezone.0, Some(tag.name), &events))
+}
+
+#[get("/location/<id>?<past_days>&<future_days>", rank = 2)]
+fn location_ics(client: GraphQLClient, site: State<Site>, timezone: VisitorTimezone, window: State<FeedWindow>, id: IcsId, past_days: Option<u32>, future_days: Option<u32>) -> error::Result<Content<Stream<Cursor<Vec<u8>>>>> {
+    let location = get_location(&client, id.0)?;
+    let events = window.with(past_days, future_days).events(&client, EventInput {
+        location_id: Some(location.id),
+        ..EventInput::default()
+    })?;
+
+    ics_response(ics_calendar(&site, timezone.0, Some(location.name), &events))
+}
+
+#[get("/organizer/<id>?<past_days>&<future_days>", rank = 2)]
+fn organizer_ics(client: GraphQLClient, site: State<Site>, timezone: VisitorTimezone, window: State<FeedWindow>, id: IcsId, past_days: Option<u32>, future_days: Option<u32>) -> error::Result<Content<Stream<Cursor<Vec<u8>>>>> {
+    let organizer = get_organizer(&client, id.0)?;
+    let events = window.with(past_days, future_days).events(&client, EventInput {
+        organizer_id: Some(organizer.id),
+        ..EventInput::default()
+    })?;
+
+    ics_response(ics_calendar(&site, timezone.0, Some(organizer.name), &events))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum FeedFormat {
+    Rss,
+    Atom,
+}
+
+/// The `feed.rss`/`feed.atom` path segment of the feeds of a tag, location or organizer.
+impl<'a> FromParam<'a> for FeedFormat {
+    type Error = &'a RawStr;
+
+    fn from_param(param: &'a RawStr) -> Result<FeedFormat, &'a RawStr> {
+        match param.as_str() {
+            "feed.rss" => Ok(FeedFormat::Rss),
+            "feed.atom" => Ok(FeedFormat::Atom),
+            _ => Err(param),
+        }
+    }
+}
+
+impl FeedFormat {
+    fn file(&self) -> &'static str {
+        match self {
+            FeedFormat::Rss => "feed.rss",
+            FeedFormat::Atom => "feed.atom",
+        }
+    }
+}
+
+impl Event {
+    /// The item of the event in RSS/Atom feeds, the content starts with the time and address.
+    fn to_feed_item(&self, site: &Site, timezone: Tz) -> FeedItem {
+        let format = |time: &NaiveDateTime| timezone.from_utc_datetime(time).format("%d.%m.%Y %H:%M").to_string();
+
+        FeedItem {
+            title: self.name.clone(),
+            link: site.url(&format!("/event/{}", self.id)),
+            content: format!(
+                "<p>{} - {}<br/>{}, {}</p>{}",
+                format(&self.time),
+                format(&self.time_end),
+                search::escape_html(&self.location.name),
+                search::escape_html(&self.location.address()),
+                markdown::render(&self.description),
+            ),
+            published: self.time,
+            categories: self.tags.iter().map(|tag| tag.name.clone()).collect(),
+        }
+    }
+}
+
+impl Event {
+    /// When and where the event takes place, the lines of its share image.
+    fn share_details(&self, timezone: Tz) -> Vec<String> {
+        vec![
+            timezone.from_utc_datetime(&self.time).format("%A, %d.%m.%Y %H:%M").to_string(),
+            format!("{}, {}", self.location.name, self.location.address()),
+        ]
+    }
+
+    /// schema.org `Event` with the times in the site's timezone.
+    fn json_ld(&self, site: &Site) -> serde_json::Value {
+        let time = |time: &NaiveDateTime| site.timezone.from_utc_datetime(time).to_rfc3339();
+        let url = site.url(&format!("/event/{}", self.id));
+
+        let mut event = serde_json::json!({
+            "@type": "Event",
+            "name": self.name,
+            "description": self.description,
+            "url": url,
+            "startDate": time(&self.time),
+            "endDate": time(&self.time_end),
+            "eventStatus": "https://schema.org/EventScheduled",
+            "eventAttendanceMode": "https://schema.org/OfflineEventAttendanceMode",
+            "location": self.location.json_ld(site),
+            "isAccessibleForFree": self.price.map_or(true, |price| price == 0),
+            "offers": {
+                "@type": "Offer",
+                "url": url,
+                "price": self.price.unwrap_or(0),
+            },
+        });
+        if let Some(currency) = &self.currency {
+            event["offers"]["priceCurrency"] = currency.as_str().into();
+        }
+        if let Some(organizer) = &self.organizer {
+            event["organizer"] = serde_json::json!({
+                "@type": "Organization",
+                "name": organizer.name,
+                "url": organizer.website.clone().unwrap_or_else(|| site.url(&format!("/organizer/{}", organizer.id))),
+            });
+        }
+        if !self.tags.is_empty() {
+            event["keywords"] = self.tags.iter().map(|tag| tag.name.as_str()).collect::<Vec<&str>>().join(", ").into();
+        }
+
+        event
+    }
+}
+
+/// Serializes schema.org data for a `<script type="application/ld+json">`, `</` is escaped so it can't end the script.
+fn json_ld_script(mut value: serde_json::Value) -> String {
+    value["@context"] = "https://schema.org".into();
+
+    value.to_string().replace("</", "<\\/")
+}
+
+/// A feed of `events`, `path` is the page the feed belongs to.
+fn feed_response(site: &Site, timezone: Tz, format: FeedFormat, title: String, path: &str, events: Vec<Event>) -> Content<String> {
+    let feed = Feed {
+        title,
+        link: site.url(path),
+        self_link: site.url(&format!("{}/{}", path.trim_end_matches('/'), format.file())),
+        items: events
+            .iter()
+            .map(|event| event.to_feed_item(site, timezone))
+            .collect(),
+    };
+
+    match format {
+        FeedFormat::Rss => Content(http::ContentType::new("application", "rss+xml"), feed.to_rss()),
+        FeedFormat::Atom => Content(http::ContentType::new("application", "atom+xml"), feed.to_atom()),
+    }
+}
+
+#[get("/feed.rss")]
+fn feed_rss(client: GraphQLClient, site: State<Site>, timezone: VisitorTimezone, window: State<FeedWindow>) -> error::Result<Content<String>> {
+    let events = window.upcoming().events(&client, EventInput::default())?;
+
+    Ok(feed_response(&site, timezone.0, FeedFormat::Rss, "Events".to_string(), "/", events))
+}
+
+#[get("/feed.atom")]
+fn feed_atom(client: GraphQLClient, site: State<Site>, timezone: VisitorTimezone, window: State<FeedWindow>) -> error::Result<Content<String>> {
+    let events = window.upcoming().events(&client, EventInput::default())?;
+
+    Ok(feed_response(&site, timezone.0, FeedFormat::Atom, "Events".to_string(), "/", events))
+}
+
+#[get("/tag/<id>/<format>", rank = 3)]
+fn tag_feed(client: GraphQLClient, site: State<Site>, timezone: VisitorTimezone, window: State<FeedWindow>, id: i64, format: FeedFormat) -> error::Result<Content<String>> {
+    let tag = get_tag(&client, id)?;
+    let mut events = window.upcoming().filter(tag.events);
+    events.sort_by_key(|event| event.time);
+
+    Ok(feed_response(&site, timezone.0, format, tag.name, &format!("/tag/{}", id), events))
+}
+
+#[get("/location/<id>/<format>", rank = 3)]
+fn location_feed(client: GraphQLClient, site: State<Site>, timezone: VisitorTimezone, window: State<FeedWindow>, id: i64, format: FeedFormat) -> error::Result<Content<String>> {
+    let location = get_location(&client, id)?;
+    let events = window.upcoming().events(&client, EventInput {
+        location_id: Some(location.id),
+        ..EventInput::default()
+    })?;
+
+    Ok(feed_response(&site, timezone.0, format, location.name, &format!("/location/{}", id), events))
+}
+
+#[get("/organizer/<id>/<format>", rank = 3)]
+fn organizer_feed(client: GraphQLClient, site: State<Site>, timezone: VisitorTimezone, window: State<FeedWindow>, id: i64, format: FeedFormat) -> error::Result<Content<String>> {
+    let organizer = get_organizer(&client, id)?;
+    let events = window.upcoming().events(&client, EventInput {
+        organizer_id: Some(organizer.id),
+        ..EventInput::default()
+    })?;
+
+    Ok(feed_response(&site, timezone.0, format, organizer.name, &format!("/organizer/{}", id), events))
+}
+
+#[get("/event/<id>/location")]
+fn event_location(client: GraphQLClient, id: i64) -> error::Result<Redirect> {
+    Ok(Redirect::permanent(format!("/location/{}", get_event(&client, id)?.location.id)))
+}
+
+#[get("/tags?<sort>&<page>")]
+fn tags(client: GraphQLClient, timezone: VisitorTimezone, meta: PageMeta, sort: Option<String>, page: Option<u32>, per_page: PerPage, flash: Option<FlashMessage<'_, '_>>) -> error::Result<Template> {
+    let sort_by_name = sort.as_deref() == Some("name");
+    let page = tag_page(&client, sort_by_name, max(1, page.unwrap_or(1)), per_page, "")?;
+
+    Ok(Template::render("tags", TagListTemplateContext {
+        title: "Tags".to_string(),
+        parent: "layout",
+        timezone: timezone.name(),
+        meta,
+        sort_by_name,
+        pagination: page.paginator,
+        tags: page.entries,
+        flash: flash_message(&client, timezone, flash),
+    }))
+}
+
+#[get("/tag/<id>")]
+fn tag(client: GraphQLClient, timezone: VisitorTimezone, meta: PageMeta, id: i64, flash: Option<FlashMessage<'_, '_>>) -> error::Result<Template> {
+    let tag = get_tag(&client, id)?;
+
+    let context = TagTemplateContext {
+        title: tag.name.clone(),
+        parent: "layout",
+        timezone: timezone.name(),
+        meta: meta.describe(&markdown::plain_text(tag.description.as_deref().unwrap_or_default())),
+        feed: format!("/tag/{}", tag.id),
+        tag,
+        flash: flash_message(&client, timezone, flash),
     };
 
-    Template::render("tag", context)
+    Ok(Template::render("tag", context))
 }
 
 #[get("/location/<id>")]
-fn location(id: i64) -> Template {
-    let location = get_location(id).unwrap(); // TODO
-    let events = get_events_for_location(location.id).unwrap();
+fn location(client: GraphQLClient, site: State<Site>, timezone: VisitorTimezone, meta: PageMeta, id: i64, flash: Option<FlashMessage<'_, '_>>) -> error::Result<Template> {
+    let location = get_location(&client, id)?;
+    let events = get_events_for_location(&client, location.id)?;
 
     let context = LocationTemplateContext {
         title: location.name.clone(),
         parent: "layout",
+        timezone: timezone.name(),
+        meta: meta.describe(&location.address()),
+        json_ld: json_ld_script(location.json_ld(&site)),
+        feed: format!("/location/{}", location.id),
         location,
         events,
+        flash: flash_message(&client, timezone, flash),
+    };
+
+    Ok(Template::render("location", context))
+}
+
+#[get("/organizer/<id>")]
+fn organizer(client: GraphQLClient, timezone: VisitorTimezone, meta: PageMeta, id: i64, flash: Option<FlashMessage<'_, '_>>) -> error::Result<Template> {
+    let organizer = get_organizer(&client, id)?;
+    let events = get_events_for_organizer(&client, organizer.id)?;
+
+    let context = OrganizerTemplateContext {
+        title: organizer.name.clone(),
+        parent: "layout",
+        timezone: timezone.name(),
+        meta: meta.describe(&format!("Events organized by {}", organizer.name)),
+        feed: format!("/organizer/{}", organizer.id),
+        organizer,
+        events,
+        flash: flash_message(&client, timezone, flash),
     };
 
-    Template::render("location", context)
+    Ok(Template::render("organizer", context))
 }
 
 #[get("/location/<id>/edit")]
-fn location_edit(id: i64) -> Template {
+fn location_edit(client: GraphQLClient, timezone: VisitorTimezone, meta: PageMeta, id: i64, flash: Option<FlashMessage<'_, '_>>) -> error::Result<Template> {
     let location = if id != 0 {
-        get_location(id).unwrap() // TODO
+        get_location(&client, id)?.into()
     } else {
-        Location {
-            id: 0,
-            name: "".to_string(),
-            website: None,
-            street: "".to_string(),
-            street_number: 0,
-            city: "".to_string(),
-            postal_code: 0,
-            country: "".to_string(),
-            building: None,
-            maps_link: "".to_string(),
-        }
+        LocationForm::default()
     };
 
-    let context = LocationTemplateContext {
-        title: location.name.clone(),
+    Ok(render_location_form(timezone, meta, id, location, FieldErrors::new(), flash_message(&client, timezone, flash)))
+}
+
+fn render_location_form(timezone: VisitorTimezone, meta: PageMeta, id: i64, location: LocationForm, errors: FieldErrors, flash: Option<String>) -> Template {
+    let context = LocationEditTemplateContext {
+        title: if id != 0 { location.name.clone() } else { "New location".to_string() },
         parent: "layout",
+        timezone: timezone.name(),
+        meta,
+        id,
         location,
-        events: vec![],
+        errors,
+        flash,
     };
 
     Template::render("edit/location", context)
 }
 
 #[post("/location/<id>/submit", data = "<location>")]
-fn location_submit(id: Option<i64>, location: Form<LocationMutation>) -> Redirect {
-    // let location = get_location(id.unwrap()).unwrap(); // TODO
-    //
-    // let context = LocationTemplateContext {
-    //     title: location.name.clone(),
-    //     parent: "layout",
-    //     location,
-    //     events: vec![],
-    // };
+fn location_submit(client: GraphQLClient, timezone: VisitorTimezone, meta: PageMeta, id: i64, location: Form<LocationForm>) -> Result<Flash<Redirect>, Template> {
+    let form = location.into_inner();
+    let mutation = match form.validate() {
+        Ok(mutation) => mutation,
+        Err(errors) => return Err(render_location_form(timezone, meta, id, form, errors, None)),
+    };
+    let result = if id != 0 {
+        mutation.update(&client, id)
+    } else {
+        mutation.add(&client)
+    };
+
+    match result {
+        Ok(location) => {
+            let message = if id != 0 {
+                format!("Updated location \"{}\"", location.name)
+            } else {
+                format!("Created location \"{}\"", location.name)
+            };
+            Ok(Flash::success(Redirect::to(format!("/location/{}", location.id)), message))
+        }
+        Err(e) => Err(render_location_form(timezone, meta, id, form, FieldErrors::new(), Some(format!("Couldn't save location: {}", e)))),
+    }
+}
+
+#[get("/event/<id>/delete")]
+fn event_delete(client: GraphQLClient, timezone: VisitorTimezone, meta: PageMeta, id: i64, flash: Option<FlashMessage<'_, '_>>) -> error::Result<Template> {
+    let event = get_event(&client, id)?;
+
+    Ok(Template::render("delete", DeleteTemplateContext {
+        title: format!("Delete {}", event.name),
+        parent: "layout",
+        timezone: timezone.name(),
+        meta,
+        kind: "event",
+        name: event.name,
+        action: format!("/event/{}/delete", id),
+        cancel: format!("/event/{}", id),
+        events: vec![],
+        locations: vec![],
+        flash: flash_message(&client, timezone, flash),
+    }))
+}
+
+#[post("/event/<id>/delete")]
+fn event_delete_submit(client: GraphQLClient, id: i64) -> Flash<Redirect> {
+    match delete_event(&client, id) {
+        Ok(()) => Flash::success(Redirect::to("/"), "Event deleted"),
+        Err(e) => Flash::error(Redirect::to(format!("/event/{}/delete", id)), format!("Couldn't delete event: {}", e)),
+    }
+}
 
-    let result = location.0.add();
-    println!("r: {:#?}", result);
+#[get("/location/<id>/delete")]
+fn location_delete(client: GraphQLClient, timezone: VisitorTimezone, meta: PageMeta, id: i64, flash: Option<FlashMessage<'_, '_>>) -> error::Result<Template> {
+    let location = get_location(&client, id)?;
+    let events = get_events_for_location(&client, id)?;
+    let locations = if events.is_empty() {
+        vec![]
+    } else {
+        get_all_locations(&client)?
+            .into_iter()
+            .filter(|other| other.id != id)
+            .map(|other| SelectOption {
+                id: other.id,
+                name: other.name,
+                selected: false,
+            })
+            .collect()
+    };
 
-    // Redirect::to("{TODO}")
-    Redirect::to("/locations")
+    Ok(Template::render("delete", DeleteTemplateContext {
+        title: format!("Delete {}", location.name),
+        parent: "layout",
+        timezone: timezone.name(),
+        meta,
+        kind: "location",
+        name: location.name,
+        action: format!("/location/{}/delete", id),
+        cancel: format!("/location/{}", id),
+        events,
+        locations,
+        flash: flash_message(&client, timezone, flash),
+    }))
 }
 
+#[post("/location/<id>/delete", data = "<form>")]
+fn location_delete_submit(client: GraphQLClient, id: i64, form: Form<DeleteForm>) -> Flash<Redirect> {
+    let error = |message: String| Flash::error(Redirect::to(format!("/location/{}/delete", id)), message);
+    let events = match get_events_for_location(&client, id) {
+        Ok(events) => events,
+        Err(e) => return error(format!("Couldn't load the events of this location: {}", e)),
+    };
+
+    if !events.is_empty() {
+        let target = match form.reassign_to {
+            Some(target) if target != id => target,
+            _ => return error(format!(
+                "This location still has {} event(s), move them to another location before deleting it",
+                events.len()
+            )),
+        };
+
+        for (moved, event) in events.into_iter().enumerate() {
+            let event_id = event.id;
+            let mut mutation = EventMutation::from(event);
+            mutation.location_id = target;
+            if let Err(e) = mutation.update(&client, event_id) {
+                return error(format!(
+                    "Couldn't move event {} to the new location: {}. The events are moved one by one, the {} before it stay moved",
+                    event_id, e, moved
+                ));
+            }
+        }
+    }
+
+    // events could have been added meanwhile, the backend shouldn't be asked to delete a location which is still used
+    match get_events_for_location(&client, id) {
+        Ok(events) if !events.is_empty() => return error(format!(
+            "This location still has {} event(s), it wasn't deleted",
+            events.len()
+        )),
+        Ok(_) if client.stale_since().is_some() => return error("The backend is unavailable, the location wasn't deleted".to_string()),
+        Ok(_) => {}
+        Err(e) => return error(format!("Couldn't check the events of this location, it wasn't deleted: {}", e)),
+    }
+
+    match delete_location(&client, id) {
+        Ok(()) => Flash::success(Redirect::to("/locations"), "Location deleted"),
+        Err(e) => error(format!("Couldn't delete location: {}", e)),
+    }
+}
+
+#[get("/organizer/<id>/delete")]
+fn organizer_delete(client: GraphQLClient, timezone: VisitorTimezone, meta: PageMeta, id: i64, flash: Option<FlashMessage<'_, '_>>) -> error::Result<Template> {
+    let organizer = get_organizer(&client, id)?;
+
+    Ok(Template::render("delete", DeleteTemplateContext {
+        title: format!("Delete {}", organizer.name),
+        parent: "layout",
+        timezone: timezone.name(),
+        meta,
+        kind: "organizer",
+        name: organizer.name,
+        action: format!("/organizer/{}/delete", id),
+        cancel: format!("/organizer/{}", id),
+        events: vec![],
+        locations: vec![],
+        flash: flash_message(&client, timezone, flash),
+    }))
+}
+
+#[post("/organizer/<id>/delete")]
+fn organizer_delete_submit(client: GraphQLClient, id: i64) -> Flash<Redirect> {
+    match delete_organizer(&client, id) {
+        Ok(()) => Flash::success(Redirect::to("/organizers"), "Organizer deleted"),
+        Err(e) => Flash::error(Redirect::to(format!("/organizer/{}/delete", id)), format!("Couldn't delete organizer: {}", e)),
+    }
+}
+
+#[get("/tag/<id>/delete")]
+fn tag_delete(client: GraphQLClient, timezone: VisitorTimezone, meta: PageMeta, id: i64, flash: Option<FlashMessage<'_, '_>>) -> error::Result<Template> {
+    let tag = get_tag(&client, id)?;
+
+    Ok(Template::render("delete", DeleteTemplateContext {
+        title: format!("Delete {}", tag.name),
+        parent: "layout",
+        timezone: timezone.name(),
+        meta,
+        kind: "tag",
+        name: tag.name,
+        action: format!("/tag/{}/delete", id),
+        cancel: format!("/tag/{}", id),
+        events: vec![],
+        locations: vec![],
+        flash: flash_message(&client, timezone, flash),
+    }))
+}
+
+#[post("/tag/<id>/delete")]
+fn tag_delete_submit(client: GraphQLClient, id: i64) -> Flash<Redirect> {
+    match delete_tag(&client, id) {
+        Ok(()) => Flash::success(Redirect::to("/tags"), "Tag deleted"),
+        Err(e) => Flash::error(Redirect::to(format!("/tag/{}/delete", id)), format!("Couldn't delete tag: {}", e)),
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct FieldChange {
+    field: &'static str,
+    existing: String,
+    imported: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ImportDuplicate {
+    /// not set if the duplicate is an earlier event of the same file
+    id: Option<i64>,
+    name: String,
+    reason: &'static str,
+    changes: Vec<FieldChange>,
+}
+
+/// An event of an uploaded calendar as shown in the import preview.
+#[derive(Debug, Serialize)]
+struct ImportCandidate {
+    index: usize,
+    name: String,
+    description: String,
+    time: NaiveDateTime,
+    time_end: NaiveDateTime,
+    location_text: Option<String>,
+    location_matched: bool,
+    locations: Vec<SelectOption>,
+    tags: Vec<InnerEventTag>,
+    unknown_categories: Vec<String>,
+    duplicate: Option<ImportDuplicate>,
+    selected: bool,
+}
+
+impl ImportCandidate {
+    fn location_id(&self) -> Option<i64> {
+        self.locations
+            .iter()
+            .find(|location| location.selected)
+            .map(|location| location.id)
+    }
+}
+
+/// Finds the location whose street, city, postal code or name appear in the free text LOCATION of an imported event.
+///
+/// At least two of them have to match, the location with the most matches wins.
+fn match_location<'a>(text: &str, locations: &'a [Location]) -> Option<&'a Location> {
+    let normalize = |value: &str| value
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ");
+    let text = format!(" {} ", normalize(text));
+    let contains = |value: &str| {
+        let value = normalize(value);
+        !value.is_empty() && text.contains(&format!(" {} ", value))
+    };
+
+    locations
+        .iter()
+        .map(|location| {
+            let matches = [
+                contains(&format!("{} {}", location.street, location.street_number)),
+                contains(&location.city),
+                contains(&location.postal_code.to_string()) || contains(&format!("{:05}", location.postal_code)),
+                contains(&location.name),
+            ];
+
+            (matches.iter().filter(|&&matched| matched).count(), location)
+        })
+        .filter(|(matches, _)| *matches >= 2)
+        .fold(None, |best: Option<(usize, &Location)>, (matches, location)| match best {
+            Some((best_matches, _)) if best_matches >= matches => best,
+            _ => Some((matches, location)),
+        })
+        .map(|(_, location)| location)
+}
+
+fn import_changes(existing: &Event, imported: &ImportCandidate, locations: &[Location]) -> Vec<FieldChange> {
+    let time = |time: &NaiveDateTime| time.format("%d.%m.%Y %H:%M").to_string();
+    let tags = |tags: &[InnerEventTag]| {
+        let mut names = tags.iter().map(|tag| tag.name.clone()).collect::<Vec<String>>();
+        names.sort();
+        names.join(", ")
+    };
+    let location = imported
+        .location_id()
+        .and_then(|id| locations.iter().find(|location| location.id == id))
+        .map(|location| location.name.clone())
+        .or_else(|| imported.location_text.clone())
+        .unwrap_or_default();
+
+    vec![
+        ("Name", existing.name.clone(), imported.name.clone()),
+        ("Description", existing.description.clone(), imported.description.clone()),
+        ("Start", time(&existing.time), time(&imported.time)),
+        ("End", time(&existing.time_end), time(&imported.time_end)),
+        ("Location", existing.location.name.clone(), location),
+        ("Tags", tags(&existing.tags), tags(&imported.tags)),
+    ]
+        .into_iter()
+        .filter(|(_, existing, imported)| existing.trim() != imported.trim())
+        .map(|(field, existing, imported)| FieldChange { field, existing, imported })
+        .collect()
+}
+
+/// Maps the parsed events onto existing locations and tags and detects the ones which already exist.
+///
+/// Events exported by this site are recognized by their UID, others by their name and start time.
+fn import_candidates(client: &GraphQLClient, site: &Site, parsed: Vec<calendar::ParsedEvent>) -> error::Result<Vec<ImportCandidate>> {
+    let events = get_all_events(client)?;
+    let locations = get_all_locations(client)?;
+    let tags = get_all_tags(client)?;
+    let uid_suffix = format!("@{}", site.uid_domain);
+    let mut seen_uids: HashMap<String, String> = HashMap::new();
+
+    Ok(parsed
+        .into_iter()
+        .enumerate()
+        .map(|(index, event)| {
+            let location = event.location.as_deref().and_then(|text| match_location(text, &locations));
+            let categories = &event.categories;
+            let unknown_categories = categories
+                .iter()
+                .filter(|category| !tags.iter().any(|tag| tag.name.eq_ignore_ascii_case(category)))
+                .cloned()
+                .collect();
+            let mut candidate = ImportCandidate {
+                index,
+                name: event.summary,
+                description: event.description,
+                time: event.start,
+                time_end: event.end,
+                location_text: event.location,
+                location_matched: location.is_some(),
+                locations: locations
+                    .iter()
+                    .map(|other| SelectOption {
+                        id: other.id,
+                        name: other.name.clone(),
+                        selected: location.map(|location| location.id) == Some(other.id),
+                    })
+                    .collect(),
+                tags: tags
+                    .iter()
+                    .filter(|tag| categories.iter().any(|category| category.eq_ignore_ascii_case(&tag.name)))
+                    .map(|tag| InnerEventTag {
+                        id: tag.id,
+                        name: tag.name.clone(),
+                        description: tag.description.clone(),
+                    })
+                    .collect(),
+                unknown_categories,
+                duplicate: None,
+                selected: false,
+            };
+
+            let by_uid = event.uid
+                .as_deref()
+                .and_then(|uid| uid.strip_prefix("event-"))
+                .and_then(|uid| uid.strip_suffix(uid_suffix.as_str()))
+                .and_then(|id| id.parse::<i64>().ok())
+                .and_then(|id| events.iter().find(|existing| existing.id == id));
+            let by_name = || events.iter().find(|existing|
+                existing.name.trim().eq_ignore_ascii_case(candidate.name.trim()) && existing.time == candidate.time);
+            let earlier = event.uid.as_ref().and_then(|uid| seen_uids.get(uid));
+
+            candidate.duplicate = match (by_uid, earlier) {
+                (Some(existing), _) => Some((existing, "UID")),
+                (None, None) => by_name().map(|existing| (existing, "name and start time")),
+                (None, Some(_)) => None,
+            }
+                .map(|(existing, reason)| ImportDuplicate {
+                    id: Some(existing.id),
+                    name: existing.name.clone(),
+                    reason,
+                    changes: import_changes(existing, &candidate, &locations),
+                })
+                .or_else(|| earlier.map(|name| ImportDuplicate {
+                    id: None,
+                    name: name.clone(),
+                    reason: "UID, it appears earlier in this file",
+                    changes: vec![],
+                }));
+            candidate.selected = candidate.duplicate.is_none() && candidate.location_matched;
+
+            if let Some(uid) = event.uid {
+                seen_uids.entry(uid).or_insert_with(|| candidate.name.clone());
+            }
+            candidate
+        })
+        .collect())
+}
+
+#[derive(Debug, FromForm)]
+struct ImportUpload {
+    calendar: String,
+}
+
+/// The confirmation of an import preview, contains the calendar again together with the selected
+/// events (`selected`, repeated) and the location chosen for each of them (`location_<index>`).
+#[derive(Debug)]
+struct ImportForm {
+    calendar: String,
+    selected: Vec<usize>,
+    locations: HashMap<usize, i64>,
+}
+
+impl<'f> FromForm<'f> for ImportForm {
+    type Error = String;
+
+    fn from_form(items: &mut FormItems<'f>, _strict: bool) -> Result<ImportForm, String> {
+        let mut form = ImportForm {
+            calendar: String::new(),
+            selected: vec![],
+            locations: HashMap::new(),
+        };
+
+        for item in items {
+            let value = item.value.url_decode().map_err(|e| format!("{}: {}", item.key, e))?;
+            let number = |value: &str| value.parse::<i64>().map_err(|e| format!("{}: {}", item.key, e));
+            match item.key.as_str() {
+                "calendar" => form.calendar = value,
+                "selected" => form.selected.push(number(&value)? as usize),
+                key => if let Some(index) = key.strip_prefix("location_") {
+                    if !value.is_empty() {
+                        form.locations.insert(number(index)? as usize, number(&value)?);
+                    }
+                },
+            }
+        }
+
+        Ok(form)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ImportTemplateContext<'a> {
+    title: String,
+    parent: &'a str,
+    timezone: String,
+    meta: PageMeta,
+    calendar: String,
+    candidates: Vec<ImportCandidate>,
+    error: Option<String>,
+    flash: Option<String>,
+}
+
+#[get("/import")]
+fn import(client: GraphQLClient, timezone: VisitorTimezone, meta: PageMeta, flash: Option<FlashMessage<'_, '_>>) -> Template {
+    Template::render("import", ImportTemplateContext {
+        title: "Import events".to_string(),
+        parent: "layout",
+        timezone: timezone.name(),
+        meta,
+        calendar: String::new(),
+        candidates: vec![],
+        error: None,
+        flash: flash_message(&client, timezone, flash),
+    })
+}
+
+#[post("/import", data = "<upload>")]
+fn import_preview(client: GraphQLClient, timezone: VisitorTimezone, meta: PageMeta, site: State<Site>, upload: Form<ImportUpload>) -> error::Result<Template> {
+    let calendar = upload.into_inner().calendar;
+    let (candidates, error) = match calendar::parse(&calendar, timezone.0) {
+        Ok(parsed) if parsed.is_empty() => (vec![], Some("The calendar doesn't contain any events".to_string())),
+        Ok(parsed) => (import_candidates(&client, &site, parsed)?, None),
+        Err(e) => (vec![], Some(format!("Couldn't read the calendar: {}", e))),
+    };
+
+    Ok(Template::render("import", ImportTemplateContext {
+        title: "Import events".to_string(),
+        parent: "layout",
+        timezone: timezone.name(),
+        meta,
+        calendar,
+        candidates,
+        error,
+        flash: flash_message(&client, timezone, None),
+    }))
+}
+
+#[post("/import/submit", data = "<form>")]
+fn import_submit(client: GraphQLClient, timezone: VisitorTimezone, site: State<Site>, form: Form<ImportForm>) -> Flash<Redirect> {
+    let error = |message: String| Flash::error(Redirect::to("/import"), message);
+    let form = form.into_inner();
+    let candidates = match calendar::parse(&form.calendar, timezone.0) {
+        Ok(parsed) => import_candidates(&client, &site, parsed),
+        Err(e) => return error(format!("Couldn't read the calendar: {}", e)),
+    };
+    let candidates = match candidates {
+        Ok(candidates) => candidates,
+        Err(e) => return error(format!("Couldn't prepare the import: {}", e)),
+    };
+
+    let mut imported = 0;
+    let mut failures = vec![];
+    for candidate in candidates.into_iter().filter(|candidate| form.selected.contains(&candidate.index)) {
+        if candidate.duplicate.is_some() {
+            failures.push(format!("\"{}\" already exists", candidate.name));
+            continue;
+        }
+        let location_id = match form.locations.get(&candidate.index) {
+            Some(id) => *id,
+            None => {
+                failures.push(format!("\"{}\" has no location", candidate.name));
+                continue;
+            }
+        };
+
+        let mutation = EventMutation {
+            name: candidate.name.clone(),
+            description: candidate.description,
+            time: candidate.time,
+            time_end: candidate.time_end,
+            price: None,
+            currency: None,
+            location_id,
+            organizer_id: None,
+            tags: candidate.tags.iter().map(|tag| tag.id).collect(),
+        };
+        match mutation.add(&client) {
+            Ok(_) => imported += 1,
+            Err(e) => failures.push(format!("\"{}\": {}", candidate.name, e)),
+        }
+    }
+
+    if failures.is_empty() {
+        Flash::success(Redirect::to("/events/1"), format!("Imported {} event(s)", imported))
+    } else {
+        error(format!("Imported {} event(s), skipped {}: {}", imported, failures.len(), failures.join(", ")))
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct TimezoneOption {
+    name: &'static str,
+    selected: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct TimezoneTemplateContext<'a> {
+    title: String,
+    parent: &'a str,
+    timezone: String,
+    meta: PageMeta,
+    /// whether the timezone was chosen by the visitor instead of being guessed
+    chosen: bool,
+    timezones: Vec<TimezoneOption>,
+    flash: Option<String>,
+}
+
+#[derive(Debug, FromForm)]
+struct TimezoneForm {
+    timezone: String,
+}
+
+/// The timezone the visitor chose on `/timezone`, if any.
+///
+/// Only borrows the cookies while the guard runs, a `Cookies` parameter would keep `FlashMessage` from reading its cookie.
+struct ChosenTimezone(Option<Tz>);
+
+impl<'a, 'r> FromRequest<'a, 'r> for ChosenTimezone {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<ChosenTimezone, ()> {
+        request::Outcome::Success(ChosenTimezone(request
+            .cookies()
+            .get(VisitorTimezone::COOKIE)
+            .and_then(|cookie| cookie.value().parse().ok())))
+    }
+}
+
+#[get("/timezone")]
+fn timezone(client: GraphQLClient, timezone: VisitorTimezone, meta: PageMeta, chosen: ChosenTimezone, flash: Option<FlashMessage<'_, '_>>) -> Template {
+    Template::render("timezone", TimezoneTemplateContext {
+        title: "Timezone".to_string(),
+        parent: "layout",
+        timezone: timezone.name(),
+        meta,
+        chosen: chosen.0.is_some(),
+        timezones: chrono_tz::TZ_VARIANTS
+            .iter()
+            .map(|variant| TimezoneOption {
+                name: variant.name(),
+                selected: chosen.0 == Some(*variant),
+            })
+            .collect(),
+        flash: flash_message(&client, timezone, flash),
+    })
+}
+
+#[post("/timezone", data = "<form>")]
+fn timezone_submit(mut cookies: Cookies, form: Form<TimezoneForm>) -> Flash<Redirect> {
+    let name = form.into_inner().timezone;
+    if name.is_empty() {
+        cookies.remove(Cookie::named(VisitorTimezone::COOKIE));
+        return Flash::success(Redirect::to("/"), "Times are shown in the default timezone again");
+    }
+
+    match name.parse::<Tz>() {
+        Ok(timezone) => {
+            cookies.add(Cookie::build(VisitorTimezone::COOKIE, timezone.name()).path("/").permanent().finish());
+            Flash::success(Redirect::to("/"), format!("Times are now shown in {}", timezone.name()))
+        }
+        Err(e) => Flash::error(Redirect::to("/timezone"), format!("Unknown timezone: {}", e)),
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorTemplateContext {
+    title: String,
+    parent: &'static str,
+    status: u16,
+    message: &'static str,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    status: u16,
+    error: &'static str,
+}
+
+/// Error pages, API requests get the error as JSON.
+#[derive(Responder)]
+enum ErrorPage {
+    Html(Template),
+    Json(Json<ErrorResponse>),
+}
+
+fn render_error(request: &Request, status: http::Status, message: &'static str) -> ErrorPage {
+    if request.uri().path().starts_with(api::PREFIX) {
+        return ErrorPage::Json(Json(ErrorResponse {
+            status: status.code,
+            error: message,
+        }));
+    }
+
+    ErrorPage::Html(Template::render("error", ErrorTemplateContext {
+        title: status.reason.to_string(),
+        parent: "layout",
+        status: status.code,
+        message,
+    }))
+}
+
+#[catch(400)]
+fn bad_request(request: &Request) -> ErrorPage {
+    render_error(request, http::Status::BadRequest, "The request was malformed.")
+}
+
+#[catch(404)]
+fn not_found(request: &Request) -> ErrorPage {
+    render_error(request, http::Status::NotFound, "This page doesn't exist.")
+}
+
+#[catch(422)]
+fn unprocessable_entity(request: &Request) -> ErrorPage {
+    render_error(request, http::Status::UnprocessableEntity, "The submitted data couldn't be processed, please check your input.")
+}
+
+#[catch(500)]
+fn internal_error(request: &Request) -> ErrorPage {
+    render_error(request, http::Status::InternalServerError, "Something went wrong on our side.")
+}
+
+#[catch(503)]
+fn service_unavailable(request: &Request) -> ErrorPage {
+    render_error(request, http::Status::ServiceUnavailable, "The event backend is currently unavailable, please try again in a few minutes.")
+}
 
 struct Session {
     test: String
@@ -811,7 +3409,7 @@ impl<'a, 'r> FromRequest<'a, 'r> for Session {
 
     fn from_request(request: &'a Request<'r>) -> request::Outcome<Session, Self::Error> {
         request::Outcome::Success(Session {
-            test: request.client_ip().unwrap_or(IpAddr::from([0, 0, 0, 0])).to_string()
+            test: request.client_ip().unwrap_or_else(|| IpAddr::from([0, 0, 0, 0])).to_string()
         })
     }
 }
@@ -827,29 +3425,78 @@ fn session(context: Session) -> Flash<Redirect> {
 fn main() {
     // get_event(1).unwrap().get_ics().write(File::create("test.ics").unwrap());
 
-    rocket::ignite()
+    let rocket = rocket::ignite();
+    let client = GraphQLClient::from_config(rocket.config()).expect("Couldn't create the backend client");
+    let feed_window = FeedWindow::from_config(rocket.config());
+    let page_sizes = PageSizes::from_config(rocket.config());
+    let site = Site::from_config(rocket.config()).expect("Invalid site configuration");
+    let share_images = share_image::Renderer::new().expect("Couldn't load the share image logo");
+
+    rocket
+        .manage(client)
+        .manage(feed_window)
+        .manage(page_sizes)
+        .manage(site)
+        .manage(share_images)
         .attach(Template::custom(|engines| {
             engines.handlebars.register_helper("time_custom", Box::new(helper_time_custom_format));
             engines.handlebars.register_helper("add", Box::new(helper_add));
-            engines.handlebars.register_helper("previousnavigation", Box::new(helper_previous_navigation));
-            engines.handlebars.register_helper("nextnavigation", Box::new(helper_next_navigation));
-            engines.handlebars.register_helper("format_description", Box::new(helper_format_description));
+            engines.handlebars.register_helper("markdown", Box::new(helper_markdown));
             engines.handlebars.register_helper("unwrap_or", Box::new(helper_unwrap_or));
         }))
         .mount("/", routes![
             event,
+            event_edit,
+            event_submit,
+            event_delete,
+            event_delete_submit,
             index,
             numbered_index,
+            archive,
+            feed_rss,
+            feed_atom,
+            tag_feed,
+            location_feed,
+            organizer_feed,
+            search,
+            events_today,
+            events_this_week,
+            events_this_month,
+            events_day,
+            events_week,
+            events_month,
             event_ics,
+            event_share_image,
+            events_ics,
+            tag_ics,
+            location_ics,
+            organizer_ics,
             event_location,
             session,
             location,
             tag,
+            tags,
             locations,
             locations_numbered,
             location_edit,
             location_submit,
+            location_delete,
+            location_delete_submit,
+            organizer,
+            organizers,
+            organizers_numbered,
+            organizer_delete,
+            organizer_delete_submit,
+            tag_delete,
+            tag_delete_submit,
+            import,
+            import_preview,
+            import_submit,
+            timezone,
+            timezone_submit,
         ])
+        .mount(api::PREFIX, api::routes())
         .mount("/public", StaticFiles::from("public/"))
+        .register(catchers![bad_request, not_found, unprocessable_entity, internal_error, service_unavailable])
         .launch();
 }
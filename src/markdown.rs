@@ -0,0 +1,179 @@
+use pulldown_cmark::{html, CowStr, Event, LinkType, Options, Parser, Tag};
+
+/// Schemes links and images may use, links with other ones (like `javascript:`) are shown as their text.
+const SAFE_SCHEMES: [&str; 3] = ["http://", "https://", "mailto:"];
+
+/// Headings of descriptions start at `<h3>`, below the headings of the page.
+const HEADING_OFFSET: u32 = 2;
+
+fn is_safe_url(url: &str) -> bool {
+    let url = url.trim().to_lowercase();
+
+    // relative urls don't have a scheme
+    url.starts_with('/') || !url.contains(':') || SAFE_SCHEMES.iter().any(|scheme| url.starts_with(scheme))
+}
+
+fn is_bare_url(word: &str) -> bool {
+    ["http://", "https://"]
+        .iter()
+        .any(|scheme| word.starts_with(scheme) && word.len() > scheme.len())
+}
+
+/// Adds the events of `text` with its bare urls turned into links.
+fn autolink<'a>(text: &str, events: &mut Vec<Event<'a>>) {
+    let mut position = 0;
+    let mut search = 0;
+
+    while let Some(offset) = text[search..].find("http") {
+        let start = search + offset;
+        let end = text[start..].find(char::is_whitespace).map_or(text.len(), |end| start + end);
+        // punctuation at the end most likely belongs to the sentence
+        let url = text[start..end].trim_end_matches(|c| ".,;:!?)".contains(c));
+        let starts_word = text[..start].chars().last().map_or(true, |c| c.is_whitespace() || c == '(');
+
+        if starts_word && is_bare_url(url) {
+            if start > position {
+                events.push(Event::Text(text[position..start].to_string().into()));
+            }
+            let url: CowStr = url.to_string().into();
+            events.push(Event::Start(Tag::Link(LinkType::Autolink, url.clone(), "".into())));
+            events.push(Event::Text(url.clone()));
+            events.push(Event::End(Tag::Link(LinkType::Autolink, url.clone(), "".into())));
+            position = start + url.len();
+        }
+        search = start + url.len();
+    }
+
+    if position < text.len() {
+        events.push(Event::Text(text[position..].to_string().into()));
+    }
+}
+
+/// The Markdown events of `text` without raw HTML and unsafe links.
+fn events(text: &str) -> Vec<Event> {
+    let mut events = Vec::new();
+    // the parser splits text at characters which could have been markup, it's joined to find the urls
+    let mut pending_text = String::new();
+    // no urls are linked inside links, images and code blocks
+    let mut verbatim = 0;
+    // whether the currently open links/images are dropped
+    let mut dropped = Vec::new();
+
+    for event in Parser::new_ext(text, Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TABLES) {
+        if let Event::Text(text) = event {
+            pending_text.push_str(&text);
+            continue;
+        }
+        if !pending_text.is_empty() {
+            if verbatim > 0 {
+                events.push(Event::Text(pending_text.clone().into()));
+            } else {
+                autolink(&pending_text, &mut events);
+            }
+            pending_text.clear();
+        }
+
+        match event {
+            Event::Html(_) => {}
+            Event::Start(Tag::Heading(level)) => events.push(Event::Start(Tag::Heading((level + HEADING_OFFSET).min(6)))),
+            Event::End(Tag::Heading(level)) => events.push(Event::End(Tag::Heading((level + HEADING_OFFSET).min(6)))),
+            Event::Start(Tag::Link(_, ref url, _)) | Event::Start(Tag::Image(_, ref url, _)) => {
+                verbatim += 1;
+                dropped.push(!is_safe_url(url));
+                if is_safe_url(url) {
+                    events.push(event);
+                }
+            }
+            Event::End(Tag::Link(..)) | Event::End(Tag::Image(..)) => {
+                verbatim -= 1;
+                if dropped.pop() != Some(true) {
+                    events.push(event);
+                }
+            }
+            Event::Start(Tag::CodeBlock(_)) => {
+                verbatim += 1;
+                events.push(event);
+            }
+            Event::End(Tag::CodeBlock(_)) => {
+                verbatim -= 1;
+                events.push(event);
+            }
+            event => events.push(event),
+        }
+    }
+    if !pending_text.is_empty() {
+        autolink(&pending_text, &mut events);
+    }
+
+    events
+}
+
+/// Renders the Markdown of a description to HTML.
+///
+/// Bare urls are linked, raw HTML is removed and links with a scheme other than http(s) or mailto are shown as text.
+pub fn render(text: &str) -> String {
+    let mut output = String::new();
+    html::push_html(&mut output, events(text).into_iter());
+
+    output
+}
+
+/// The text of a description without its markup, e.g. for meta tags.
+pub fn plain_text(text: &str) -> String {
+    let mut output = String::new();
+
+    for event in events(text) {
+        match event {
+            Event::Text(text) | Event::Code(text) => output.push_str(&text),
+            Event::End(Tag::Emphasis) | Event::End(Tag::Strong) | Event::End(Tag::Strikethrough) | Event::End(Tag::Link(..)) | Event::End(Tag::Image(..)) => {}
+            Event::SoftBreak | Event::HardBreak | Event::End(_) => output.push(' '),
+            _ => {}
+        }
+    }
+
+    output.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{plain_text, render};
+
+    #[test]
+    fn drops_javascript_links() {
+        assert_eq!(render("[click](javascript:alert(1))"), "<p>click</p>\n");
+        assert_eq!(render("[click]( JavaScript:alert(1))"), "<p>click</p>\n");
+        assert_eq!(render("![image](javascript:alert(1))"), "<p>image</p>\n");
+    }
+
+    #[test]
+    fn keeps_safe_links() {
+        assert_eq!(render("[site](https://example.org)"), "<p><a href=\"https://example.org\">site</a></p>\n");
+        assert_eq!(render("[mail](mailto:a@example.org)"), "<p><a href=\"mailto:a@example.org\">mail</a></p>\n");
+        assert_eq!(render("[page](/event/1)"), "<p><a href=\"/event/1\">page</a></p>\n");
+    }
+
+    #[test]
+    fn drops_raw_html() {
+        assert_eq!(render("<script>alert(1)</script>"), "");
+        assert_eq!(render("Hi <b onclick=\"alert(1)\">there</b>"), "<p>Hi there</p>\n");
+    }
+
+    #[test]
+    fn links_bare_urls() {
+        assert_eq!(
+            render("See https://example.org."),
+            "<p>See <a href=\"https://example.org\">https://example.org</a>.</p>\n"
+        );
+        assert_eq!(render("`https://example.org`"), "<p><code>https://example.org</code></p>\n");
+    }
+
+    #[test]
+    fn shifts_headings() {
+        assert_eq!(render("# Title"), "<h3>Title</h3>\n");
+    }
+
+    #[test]
+    fn strips_markup_from_plain_text() {
+        assert_eq!(plain_text("# Title\n\nSome *text* with [a link](javascript:x).\n<i>raw</i>"), "Title Some text with a link. raw");
+    }
+}
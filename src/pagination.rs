@@ -1,8 +1,12 @@
-use std::io;
+use std::convert::TryFrom;
 
-use graphql_client::{GraphQLQuery, Response};
+use graphql_client::GraphQLQuery;
+use rocket::{Config, Request, State, request};
+use rocket::http::Cookie;
+use rocket::request::FromRequest;
 
-use crate::backend_url;
+use crate::GraphQLClient;
+use crate::error::{Error, Result};
 
 #[derive(Clone, Debug, Deserialize, GraphQLQuery, Serialize)]
 #[graphql(
@@ -16,35 +20,10 @@ pub struct Pagination {
     pub organizer_count: i64,
 }
 
-pub(crate) fn get_pagination() -> io::Result<Pagination> {
-    let ioerror = |desc: &str| io::Error::new(io::ErrorKind::Other, desc);
-    let body = Pagination::build_query(pagination::Variables {});
-
-    let client = reqwest::blocking::Client::new();
-    let res = match client.post(&backend_url()).json(&body).send() {
-        Ok(val) => Ok(val),
-        Err(e) => Err(ioerror(format!("{:#?}", e).as_ref()))
-    }?;
-    let response: Response<pagination::ResponseData> = res.json().map_err(|e|
-        ioerror(format!("Couldn't get successful response from server: {}", e).as_ref())
-    )?;
-    let data = response.data.ok_or(
-        ioerror(
-            format!(
-                "Couldn't get data field from response: {:?}",
-                response
-                    .errors
-                    .and_then(|x|
-                        Some(x
-                            .into_iter()
-                            .map(|x|
-                                x.message)
-                            .collect::<Vec<String>>()
-                            .join(" | ")
-                        ))).as_ref())
-    )?;
-
-    Ok(From::from(data.pagination))
+pub fn get_pagination(client: &GraphQLClient) -> Result<Pagination> {
+    let data = client.query::<Pagination>(pagination::Variables {})?;
+
+    Pagination::try_from(data.pagination)
 }
 
 // TODO: move this
@@ -54,6 +33,16 @@ pub struct PaginationContext {
     pub offset: u32,
 }
 
+impl PaginationContext {
+    /// The entries shown on `page` (starting at 1) with `limit` entries per page.
+    pub fn page(page: u32, limit: u32) -> PaginationContext {
+        PaginationContext {
+            limit,
+            offset: limit.saturating_mul(page.max(1) - 1),
+        }
+    }
+}
+
 impl Default for PaginationContext {
     fn default() -> Self {
         PaginationContext {
@@ -63,12 +52,258 @@ impl Default for PaginationContext {
     }
 }
 
-impl From<pagination::PaginationPagination> for Pagination {
-    fn from(input: pagination::PaginationPagination) -> Pagination {
-        Pagination {
-            event_count: input.event_count.parse().unwrap(),
-            location_count: input.location_count.parse().unwrap(),
-            organizer_count: input.organizer_count.parse().unwrap(),
+impl TryFrom<pagination::PaginationPagination> for Pagination {
+    type Error = Error;
+
+    fn try_from(input: pagination::PaginationPagination) -> Result<Pagination> {
+        let count = |value: String| value.parse().map_err(|e| Error::Decode(format!("invalid count {:?}: {}", value, e)));
+
+        Ok(Pagination {
+            event_count: count(input.event_count)?,
+            location_count: count(input.location_count)?,
+            organizer_count: count(input.organizer_count)?,
+        })
+    }
+}
+
+/// Bounds of the page size visitors can choose with `?per_page=`.
+#[derive(Clone, Copy, Debug)]
+pub struct PageSizes {
+    pub default: u32,
+    pub min: u32,
+    pub max: u32,
+}
+
+impl PageSizes {
+    /// Uses the `per_page_default`, `per_page_min` and `per_page_max` settings of the rocket config.
+    pub fn from_config(config: &Config) -> PageSizes {
+        let setting = |name: &str, default: u32| config
+            .get_int(name)
+            .map(|value| value.max(1).min(i64::from(u32::MAX)) as u32)
+            .unwrap_or(default);
+        let min = setting("per_page_min", 10);
+        let max = setting("per_page_max", 500).max(min);
+
+        PageSizes {
+            default: setting("per_page_default", PaginationContext::default().limit).max(min).min(max),
+            min,
+            max,
         }
     }
 }
+
+/// The page size of a listing, taken from `?per_page=`, the cookie remembering the last choice or the configured default.
+#[derive(Clone, Copy, Debug)]
+pub struct PerPage {
+    pub limit: u32,
+    default: u32,
+}
+
+impl PerPage {
+    pub const COOKIE: &'static str = "per_page";
+
+    /// Appends the page size to a link unless it's the default.
+    pub fn link(&self, href: &str) -> String {
+        if self.limit == self.default {
+            href.to_string()
+        } else {
+            let separator = if href.contains('?') { '&' } else { '?' };
+            format!("{}{}per_page={}", href, separator, self.limit)
+        }
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for PerPage {
+    type Error = ();
+
+    /// An explicit `?per_page=` is stored in a cookie, values outside of the configured bounds are clamped.
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<PerPage, ()> {
+        request
+            .guard::<State<PageSizes>>()
+            .map(|sizes| {
+                let clamp = |limit: u32| limit.max(sizes.min).min(sizes.max);
+                let mut cookies = request.cookies();
+                let limit = match request.get_query_value::<u32>("per_page").and_then(|limit| limit.ok()) {
+                    Some(limit) => {
+                        let limit = clamp(limit);
+                        cookies.add(Cookie::build(PerPage::COOKIE, limit.to_string()).path("/").permanent().finish());
+                        limit
+                    }
+                    None => cookies
+                        .get(PerPage::COOKIE)
+                        .and_then(|cookie| cookie.value().parse().ok())
+                        .map(clamp)
+                        .unwrap_or(sizes.default),
+                };
+
+                PerPage {
+                    limit,
+                    default: sizes.default,
+                }
+            })
+    }
+}
+
+/// Number of page links shown on each side of the current page.
+const PAGE_WINDOW: u32 = 2;
+
+#[derive(Debug, Serialize)]
+pub struct PageLink {
+    pub number: u32,
+    pub href: String,
+    pub current: bool,
+}
+
+/// Navigation for a list of `total` entries split into pages of `limit` entries, ready to be rendered by the `pagination` partial.
+///
+/// `first` and `last` are only set if they aren't part of the window of `pages` around the current page.
+#[derive(Debug, Serialize)]
+pub struct Paginator {
+    pub page: u32,
+    pub per_page: u32,
+    pub total: i64,
+    pub total_pages: u32,
+    pub label: String,
+    pub first: Option<PageLink>,
+    pub previous: Option<PageLink>,
+    pub pages: Vec<PageLink>,
+    pub next: Option<PageLink>,
+    pub last: Option<PageLink>,
+    /// whether pages are left out between `first` and the window
+    pub gap_before: bool,
+    /// whether pages are left out between the window and `last`
+    pub gap_after: bool,
+}
+
+impl Paginator {
+    /// `link` returns the href of a page number.
+    pub fn new(page: u32, limit: u32, total: i64, link: &dyn Fn(u32) -> String) -> Paginator {
+        let page = page.max(1);
+        let per_page = limit.max(1);
+        let total = total.max(0);
+        // an empty list still has one (empty) page
+        let total_pages = ((total as u64 + u64::from(per_page) - 1) / u64::from(per_page)).max(1).min(u64::from(u32::MAX)) as u32;
+
+        let page_link = |number: u32| PageLink {
+            number,
+            href: link(number),
+            current: number == page,
+        };
+
+        let window_start = page.min(total_pages).saturating_sub(PAGE_WINDOW).max(1);
+        let window_end = page.saturating_add(PAGE_WINDOW).min(total_pages);
+
+        Paginator {
+            page,
+            per_page,
+            total,
+            total_pages,
+            label: format!("Page {} of {}", page, total_pages),
+            first: Some(page_link(1)).filter(|_| window_start > 1),
+            previous: Some(page - 1).filter(|previous| *previous >= 1).map(page_link),
+            pages: (window_start..=window_end).map(page_link).collect(),
+            next: Some(page.saturating_add(1)).filter(|next| *next <= total_pages).map(page_link),
+            last: Some(page_link(total_pages)).filter(|_| window_end < total_pages),
+            gap_before: window_start > 2,
+            gap_after: window_end.saturating_add(1) < total_pages,
+        }
+    }
+}
+
+/// The pagination metadata of the JSON API.
+#[derive(Debug, Serialize)]
+pub struct PageMetadata {
+    pub page: u32,
+    pub per_page: u32,
+    pub total: i64,
+    pub total_pages: u32,
+    pub previous: Option<String>,
+    pub next: Option<String>,
+}
+
+impl From<Paginator> for PageMetadata {
+    fn from(paginator: Paginator) -> PageMetadata {
+        PageMetadata {
+            page: paginator.page,
+            per_page: paginator.per_page,
+            total: paginator.total,
+            total_pages: paginator.total_pages,
+            previous: paginator.previous.map(|link| link.href),
+            next: paginator.next.map(|link| link.href),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PaginationContext, Paginator};
+
+    fn paginator(page: u32, total: i64) -> Paginator {
+        Paginator::new(page, 10, total, &|number| format!("/list?page={}", number))
+    }
+
+    fn numbers(paginator: &Paginator) -> Vec<u32> {
+        paginator.pages.iter().map(|link| link.number).collect()
+    }
+
+    #[test]
+    fn first_page() {
+        let paginator = paginator(1, 100);
+
+        assert_eq!(paginator.total_pages, 10);
+        assert_eq!(numbers(&paginator), vec![1, 2, 3]);
+        assert!(paginator.pages[0].current);
+        assert!(paginator.first.is_none());
+        assert!(paginator.previous.is_none());
+        assert_eq!(paginator.next.as_ref().map(|link| link.href.as_str()), Some("/list?page=2"));
+        assert_eq!(paginator.last.as_ref().map(|link| link.number), Some(10));
+        assert!(!paginator.gap_before);
+        assert!(paginator.gap_after);
+    }
+
+    #[test]
+    fn last_page() {
+        let paginator = paginator(10, 95);
+
+        assert_eq!(paginator.total_pages, 10);
+        assert_eq!(numbers(&paginator), vec![8, 9, 10]);
+        assert_eq!(paginator.first.as_ref().map(|link| link.number), Some(1));
+        assert_eq!(paginator.previous.as_ref().map(|link| link.number), Some(9));
+        assert!(paginator.next.is_none());
+        assert!(paginator.last.is_none());
+        assert!(paginator.gap_before);
+        assert!(!paginator.gap_after);
+        assert_eq!(paginator.label, "Page 10 of 10");
+    }
+
+    #[test]
+    fn empty_list() {
+        let paginator = paginator(1, 0);
+
+        assert_eq!(paginator.total_pages, 1);
+        assert_eq!(numbers(&paginator), vec![1]);
+        assert!(paginator.first.is_none());
+        assert!(paginator.previous.is_none());
+        assert!(paginator.next.is_none());
+        assert!(paginator.last.is_none());
+        assert!(!paginator.gap_before && !paginator.gap_after);
+    }
+
+    #[test]
+    fn window_touching_the_ends() {
+        let paginator = paginator(4, 70);
+
+        assert_eq!(numbers(&paginator), vec![2, 3, 4, 5, 6]);
+        assert_eq!(paginator.first.as_ref().map(|link| link.number), Some(1));
+        assert_eq!(paginator.last.as_ref().map(|link| link.number), Some(7));
+        assert!(!paginator.gap_before);
+        assert!(!paginator.gap_after);
+    }
+
+    #[test]
+    fn offset_of_page() {
+        assert_eq!(PaginationContext::page(0, 20).offset, 0);
+        assert_eq!(PaginationContext::page(3, 20).offset, 40);
+        assert_eq!(PaginationContext::page(u32::MAX, 20).offset, u32::MAX);
+    }
+}
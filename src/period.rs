@@ -0,0 +1,178 @@
+use std::convert::TryFrom;
+use std::ops::RangeInclusive;
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+/// Years that can be browsed, keeps the neighbouring periods well inside of what chrono can represent.
+const YEARS: RangeInclusive<i32> = 1..=9999;
+
+/// A calendar day, ISO week or month the events can be browsed by.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Period {
+    Day(NaiveDate),
+    /// ISO year and week number
+    Week(i32, u32),
+    /// year and month
+    Month(i32, u32),
+}
+
+impl Period {
+    /// Parses `yyyy-mm-dd`.
+    pub fn day(value: &str) -> Option<Period> {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .ok()
+            .filter(|date| YEARS.contains(&date.year()))
+            .map(Period::Day)
+    }
+
+    /// Parses ISO weeks like `2020-W07` (the `W` is optional).
+    pub fn week(value: &str) -> Option<Period> {
+        let mut parts = value.splitn(2, '-');
+        let year = parts.next()?.parse().ok().filter(|year| YEARS.contains(year))?;
+        let week = parts.next()?;
+        let week = week.strip_prefix(|c| c == 'W' || c == 'w').unwrap_or(week).parse().ok()?;
+
+        NaiveDate::from_isoywd_opt(year, week, Weekday::Mon).map(|_| Period::Week(year, week))
+    }
+
+    /// Parses `yyyy-mm`.
+    pub fn month(value: &str) -> Option<Period> {
+        let mut parts = value.splitn(2, '-');
+        let year = parts.next()?.parse().ok().filter(|year| YEARS.contains(year))?;
+        let month = parts.next()?.parse().ok()?;
+
+        NaiveDate::from_ymd_opt(year, month, 1).map(|_| Period::Month(year, month))
+    }
+
+    /// The month `months` months before the one of `date`, `None` if it's before year 1.
+    pub fn months_before(date: NaiveDate, months: u32) -> Option<Period> {
+        let index = i64::from(date.year()) * 12 + i64::from(date.month0()) - i64::from(months);
+        let year = i32::try_from(index.div_euclid(12)).ok().filter(|year| YEARS.contains(year))?;
+
+        Some(Period::Month(year, index.rem_euclid(12) as u32 + 1))
+    }
+
+    pub fn week_of(date: NaiveDate) -> Period {
+        Period::Week(date.iso_week().year(), date.iso_week().week())
+    }
+
+    pub fn month_of(date: NaiveDate) -> Period {
+        Period::Month(date.year(), date.month())
+    }
+
+    pub fn first_day(&self) -> NaiveDate {
+        match *self {
+            Period::Day(date) => date,
+            Period::Week(year, week) => NaiveDate::from_isoywd(year, week, Weekday::Mon),
+            Period::Month(year, month) => NaiveDate::from_ymd(year, month, 1),
+        }
+    }
+
+    /// The day after the period.
+    pub fn end(&self) -> NaiveDate {
+        self.next().first_day()
+    }
+
+    pub fn previous(&self) -> Period {
+        match *self {
+            Period::Day(date) => Period::Day(date - Duration::days(1)),
+            Period::Week(_, _) => Period::week_of(self.first_day() - Duration::weeks(1)),
+            Period::Month(year, 1) => Period::Month(year - 1, 12),
+            Period::Month(year, month) => Period::Month(year, month - 1),
+        }
+    }
+
+    pub fn next(&self) -> Period {
+        match *self {
+            Period::Day(date) => Period::Day(date + Duration::days(1)),
+            Period::Week(_, _) => Period::week_of(self.first_day() + Duration::weeks(1)),
+            Period::Month(year, 12) => Period::Month(year + 1, 1),
+            Period::Month(year, month) => Period::Month(year, month + 1),
+        }
+    }
+
+    /// The path of the event view of this period.
+    pub fn path(&self) -> String {
+        match *self {
+            Period::Day(date) => format!("/events/day/{}", date.format("%Y-%m-%d")),
+            Period::Week(year, week) => format!("/events/week/{}-W{:02}", year, week),
+            Period::Month(year, month) => format!("/events/month/{}-{:02}", year, month),
+        }
+    }
+
+    pub fn title(&self) -> String {
+        match *self {
+            Period::Day(date) => date.format("%A, %d.%m.%Y").to_string(),
+            Period::Week(year, week) => {
+                let last_day = self.end() - Duration::days(1);
+                format!("Week {}, {} ({} - {})", week, year, self.first_day().format("%d.%m."), last_day.format("%d.%m.%Y"))
+            }
+            Period::Month(_, _) => self.first_day().format("%B %Y").to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::Period;
+
+    #[test]
+    fn parses_paths() {
+        assert_eq!(Period::day("2021-05-01"), Some(Period::Day(NaiveDate::from_ymd(2021, 5, 1))));
+        assert_eq!(Period::week("2021-W07"), Some(Period::Week(2021, 7)));
+        assert_eq!(Period::week("2021-7"), Some(Period::Week(2021, 7)));
+        assert_eq!(Period::month("2021-05"), Some(Period::Month(2021, 5)));
+        assert_eq!(Period::month("2021-13"), None);
+        assert_eq!(Period::day("2021-02-30"), None);
+    }
+
+    #[test]
+    fn rejects_years_out_of_range() {
+        assert_eq!(Period::day("10000-01-01"), None);
+        assert_eq!(Period::week("0-W01"), None);
+        assert_eq!(Period::month("262143-12"), None);
+        assert_eq!(Period::month("9999-12").map(|month| month.next()), Some(Period::Month(10000, 1)));
+    }
+
+    #[test]
+    fn week_53() {
+        let week = Period::week("2020-W53").unwrap();
+
+        assert_eq!(week.first_day(), NaiveDate::from_ymd(2020, 12, 28));
+        assert_eq!(week.end(), NaiveDate::from_ymd(2021, 1, 4));
+        assert_eq!(week.next(), Period::Week(2021, 1));
+        assert_eq!(Period::Week(2021, 1).previous(), week);
+        assert_eq!(Period::week_of(NaiveDate::from_ymd(2021, 1, 3)), week);
+        assert_eq!(Period::week("2021-W53"), None);
+    }
+
+    #[test]
+    fn rolls_over_the_year() {
+        assert_eq!(Period::Month(2020, 12).next(), Period::Month(2021, 1));
+        assert_eq!(Period::Month(2021, 1).previous(), Period::Month(2020, 12));
+        assert_eq!(Period::Month(2020, 12).end(), NaiveDate::from_ymd(2021, 1, 1));
+        assert_eq!(Period::Day(NaiveDate::from_ymd(2020, 12, 31)).next(), Period::Day(NaiveDate::from_ymd(2021, 1, 1)));
+        assert_eq!(Period::Week(2021, 52).next(), Period::Week(2022, 1));
+    }
+
+    #[test]
+    fn months_before() {
+        let date = NaiveDate::from_ymd(2021, 2, 14);
+
+        assert_eq!(Period::months_before(date, 0), Some(Period::Month(2021, 2)));
+        assert_eq!(Period::months_before(date, 2), Some(Period::Month(2020, 12)));
+        assert_eq!(Period::months_before(date, 26), Some(Period::Month(2018, 12)));
+        assert_eq!(Period::months_before(NaiveDate::from_ymd(1, 3, 1), 2), Some(Period::Month(1, 1)));
+        assert_eq!(Period::months_before(NaiveDate::from_ymd(1, 3, 1), 3), None);
+        assert_eq!(Period::months_before(date, u32::MAX), None);
+    }
+
+    #[test]
+    fn paths() {
+        assert_eq!(Period::Week(2021, 7).path(), "/events/week/2021-W07");
+        assert_eq!(Period::Month(2021, 5).path(), "/events/month/2021-05");
+        assert_eq!(Period::Day(NaiveDate::from_ymd(2021, 5, 1)).path(), "/events/day/2021-05-01");
+    }
+}
@@ -0,0 +1,116 @@
+/// Splits a search query into lowercase terms.
+pub fn terms(query: &str) -> Vec<String> {
+    query
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Whether every term occurs in at least one of the fields, ignoring case.
+pub fn matches(terms: &[String], fields: &[&str]) -> bool {
+    let fields = fields
+        .iter()
+        .map(|field| field.to_lowercase())
+        .collect::<Vec<String>>();
+
+    !terms.is_empty() && terms.iter().all(|term| fields.iter().any(|field| field.contains(term.as_str())))
+}
+
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#x27;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// The end of `term` if `text` matches it (ignoring case) starting at the byte `start`.
+fn match_at(text: &str, start: usize, term: &str) -> Option<usize> {
+    let mut term_chars = term.chars().peekable();
+
+    for (offset, c) in text[start..].char_indices() {
+        for lower in c.to_lowercase() {
+            if term_chars.next() != Some(lower) {
+                return None;
+            }
+        }
+        if term_chars.peek().is_none() {
+            return Some(start + offset + c.len_utf8());
+        }
+    }
+
+    None
+}
+
+/// Sorted, non-overlapping byte ranges of `text` matching any of the terms.
+fn match_ranges(text: &str, terms: &[String]) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+
+    for (start, _) in text.char_indices() {
+        let end = terms
+            .iter()
+            .filter(|term| !term.is_empty())
+            .filter_map(|term| match_at(text, start, term))
+            .max();
+
+        if let Some(end) = end {
+            match ranges.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => ranges.push((start, end)),
+            }
+        }
+    }
+
+    ranges
+}
+
+/// Escapes `text` for HTML and wraps the matched terms in `<mark>`.
+pub fn highlight(text: &str, terms: &[String]) -> String {
+    let mut result = String::new();
+    let mut position = 0;
+
+    for (start, end) in match_ranges(text, terms) {
+        result.push_str(&escape_html(&text[position..start]));
+        result.push_str("<mark>");
+        result.push_str(&escape_html(&text[start..end]));
+        result.push_str("</mark>");
+        position = end;
+    }
+    result.push_str(&escape_html(&text[position..]));
+
+    result
+}
+
+/// Like `highlight`, but long texts are cut to about `length` characters around the first match.
+pub fn snippet(text: &str, terms: &[String], length: usize) -> String {
+    let chars = text.char_indices().map(|(index, _)| index).collect::<Vec<usize>>();
+    if chars.len() <= length {
+        return highlight(text, terms);
+    }
+
+    let first_match = match_ranges(text, terms)
+        .first()
+        .map_or(0, |(start, _)| chars.iter().position(|index| index == start).unwrap_or(0));
+    // show some context before the match
+    let start = first_match.saturating_sub(length / 4).min(chars.len() - length);
+    let end = start + length;
+
+    let mut result = String::new();
+    if start > 0 {
+        result.push('…');
+    }
+    result.push_str(&highlight(&text[chars[start]..chars.get(end).copied().unwrap_or_else(|| text.len())], terms));
+    if end < chars.len() {
+        result.push('…');
+    }
+
+    result
+}
@@ -0,0 +1,178 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io;
+
+use image::{DynamicImage, ImageOutputFormat, Rgba, RgbaImage};
+use image::imageops::{self, FilterType};
+use rusttype::{Font, Point, Scale};
+
+/// Size recommended for Open Graph images.
+pub const WIDTH: u32 = 1200;
+pub const HEIGHT: u32 = 630;
+
+const MARGIN: u32 = 64;
+const LOGO_SIZE: u32 = 96;
+const TITLE_SIZE: f32 = 72.0;
+const TITLE_LINES: usize = 3;
+const DETAIL_SIZE: f32 = 38.0;
+
+const BACKGROUND: Rgba<u8> = Rgba([0x41, 0x41, 0x41, 0xff]);
+const HEADER: Rgba<u8> = Rgba([0x36, 0x36, 0x36, 0xff]);
+const TITLE: Rgba<u8> = Rgba([0xf0, 0xf0, 0xf0, 0xff]);
+const TEXT: Rgba<u8> = Rgba([0xa3, 0x99, 0x89, 0xff]);
+
+const LOGO: &[u8] = include_bytes!("../public/header.png");
+const BOLD: &[u8] = include_bytes!("../resources/fonts/DejaVuSans-Bold.ttf");
+const REGULAR: &[u8] = include_bytes!("../resources/fonts/DejaVuSans.ttf");
+
+fn font(data: &'static [u8]) -> Font<'static> {
+    Font::try_from_bytes(data).expect("the bundled font is valid")
+}
+
+fn text_width(font: &Font, scale: Scale, text: &str) -> f32 {
+    font.layout(text, scale, Point { x: 0.0, y: 0.0 })
+        .last()
+        .map_or(0.0, |glyph| glyph.position().x + glyph.unpositioned().h_metrics().advance_width)
+}
+
+/// Cuts `text` so it fits into `width`, ending it with an ellipsis if anything was removed.
+fn truncate(font: &Font, scale: Scale, text: &str, width: f32) -> String {
+    if text_width(font, scale, text) <= width {
+        return text.to_string();
+    }
+
+    let mut text = text.to_string();
+    while !text.is_empty() && text_width(font, scale, &format!("{}…", text)) > width {
+        text.pop();
+    }
+    format!("{}…", text.trim_end())
+}
+
+/// Breaks `text` into at most `max_lines` lines of `width`, the last line is truncated if it doesn't fit.
+fn wrap(font: &Font, scale: Scale, text: &str, width: f32, max_lines: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut words = text.split_whitespace();
+
+    while let Some(word) = words.next() {
+        let candidate = match lines.last() {
+            Some(line) => format!("{} {}", line, word),
+            None => word.to_string(),
+        };
+
+        if lines.is_empty() {
+            lines.push(candidate);
+        } else if text_width(font, scale, &candidate) <= width {
+            *lines.last_mut().unwrap() = candidate;
+        } else if lines.len() < max_lines {
+            lines.push(word.to_string());
+        } else {
+            // no space left, show that the text goes on
+            let rest = words.map(|word| format!(" {}", word)).collect::<String>();
+            *lines.last_mut().unwrap() = format!("{} {}{}", lines.last().unwrap(), word, rest);
+            break;
+        }
+    }
+
+    lines
+        .into_iter()
+        .map(|line| truncate(font, scale, &line, width))
+        .collect()
+}
+
+fn draw_text(canvas: &mut RgbaImage, font: &Font, scale: Scale, color: Rgba<u8>, x: u32, baseline: u32, text: &str) {
+    let start = Point { x: x as f32, y: baseline as f32 };
+
+    for glyph in font.layout(text, scale, start) {
+        if let Some(bounds) = glyph.pixel_bounding_box() {
+            glyph.draw(|glyph_x, glyph_y, coverage| {
+                let x = bounds.min.x + glyph_x as i32;
+                let y = bounds.min.y + glyph_y as i32;
+                if x < 0 || y < 0 || x >= canvas.width() as i32 || y >= canvas.height() as i32 {
+                    return;
+                }
+
+                let pixel = canvas.get_pixel_mut(x as u32, y as u32);
+                for channel in 0..3 {
+                    let background = f32::from(pixel[channel]);
+                    pixel[channel] = (background + (f32::from(color[channel]) - background) * coverage).round() as u8;
+                }
+            });
+        }
+    }
+}
+
+/// Identifies the image of `title` and `details`, as quoted HTTP entity tag.
+///
+/// Changes with the version of the site, the rendering may differ between them.
+pub fn etag(title: &str, details: &[String]) -> String {
+    let mut hasher = DefaultHasher::new();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    title.hash(&mut hasher);
+    details.hash(&mut hasher);
+
+    format!("\"{:016x}\"", hasher.finish())
+}
+
+/// The fonts and the scaled logo, loaded once and shared by all images.
+pub struct Renderer {
+    bold: Font<'static>,
+    regular: Font<'static>,
+    logo: RgbaImage,
+}
+
+impl Renderer {
+    pub fn new() -> io::Result<Renderer> {
+        let logo = image::load_from_memory(LOGO)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+            .to_rgba8();
+        let logo_width = logo.width() * LOGO_SIZE / logo.height().max(1);
+
+        Ok(Renderer {
+            bold: font(BOLD),
+            regular: font(REGULAR),
+            logo: imageops::resize(&logo, logo_width, LOGO_SIZE, FilterType::Lanczos3),
+        })
+    }
+
+    /// Renders a PNG with the site's branding, `title` in large letters and a line per entry of `details`.
+    pub fn render(&self, title: &str, details: &[String]) -> io::Result<Vec<u8>> {
+        let (bold, regular, logo) = (&self.bold, &self.regular, &self.logo);
+        let max_width = (WIDTH - 2 * MARGIN) as f32;
+
+        let mut canvas = RgbaImage::from_pixel(WIDTH, HEIGHT, BACKGROUND);
+        let header_height = LOGO_SIZE + MARGIN / 2;
+        for (_, y, pixel) in canvas.enumerate_pixels_mut() {
+            if y < header_height {
+                *pixel = HEADER;
+            }
+        }
+
+        let logo_width = logo.width();
+        imageops::overlay(&mut canvas, logo, MARGIN, MARGIN / 4);
+        draw_text(&mut canvas, bold, Scale::uniform(48.0), TEXT, MARGIN + logo_width + 24, MARGIN / 4 + LOGO_SIZE / 2 + 17, "Events");
+
+        let title_scale = Scale::uniform(TITLE_SIZE);
+        let title_line_height = (TITLE_SIZE * 1.2) as u32;
+        let mut baseline = header_height + MARGIN + TITLE_SIZE as u32;
+        for line in wrap(bold, title_scale, title, max_width, TITLE_LINES) {
+            draw_text(&mut canvas, bold, title_scale, TITLE, MARGIN, baseline, &line);
+            baseline += title_line_height;
+        }
+
+        // the details are aligned to the bottom
+        let detail_scale = Scale::uniform(DETAIL_SIZE);
+        let detail_line_height = (DETAIL_SIZE * 1.4) as u32;
+        let mut baseline = HEIGHT - MARGIN - detail_line_height * (details.len() as u32).saturating_sub(1);
+        for detail in details {
+            draw_text(&mut canvas, regular, detail_scale, TEXT, MARGIN, baseline, &truncate(regular, detail_scale, detail, max_width));
+            baseline += detail_line_height;
+        }
+
+        let mut png = Vec::new();
+        DynamicImage::ImageRgba8(canvas)
+            .write_to(&mut png, ImageOutputFormat::Png)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+        Ok(png)
+    }
+}